@@ -1,10 +1,15 @@
+use std::time::{Duration, Instant};
+
 use crate::{
     board::{
         coordinates::{ColumnIndex::*, Move, RowIndex::*},
         grid::board_iterator,
+        moves::MoveSignal,
+        outcome::GameOutcome,
         piece::Colour::{self, *},
         BoardState,
     },
+    clock::{Clock, Delay},
     display::Display,
     Player,
 };
@@ -15,40 +20,158 @@ impl Display for NoDisplay {
     fn get_unique_id(&self) -> u32 {
         0
     }
-    fn display_board(&self, _: &BoardState) {}
-    fn display_checkmate(&self, _: Colour) {}
+    fn display_board(&self, _: &BoardState, _: Option<&Clock>) {}
+    fn display_game_over(&self, _: GameOutcome) {}
+    fn display_warning(&self, _: &str) {}
 }
 
 #[derive(Debug)]
 pub struct AiPlayer {
     depth: u8,
+    /// The longest time to spend searching for each move, if limited
+    time_limit: Option<Duration>,
 }
 
 impl AiPlayer {
     pub fn new(depth: u8) -> Self {
-        AiPlayer { depth }
+        AiPlayer {
+            depth,
+            time_limit: None,
+        }
+    }
+
+    /// Return this player, searching deeper one level at a time until `time_limit` has passed
+    /// (up to its depth), and playing the best move of the deepest completed search
+    ///
+    /// Note: the first level is always searched in full, so that there is a move to play.
+    pub fn with_time_limit(self, time_limit: Duration) -> Self {
+        AiPlayer {
+            time_limit: Some(time_limit),
+            ..self
+        }
+    }
+
+    /// Return the move to play in the current position
+    ///
+    /// Note: panics if there are no legal moves (i.e. in checkmate or stalemate)
+    pub fn choose_move(&self, board_state: &mut BoardState) -> Move {
+        self.search(board_state, self.time_limit)
+    }
+
+    /// Return the best move found within `time_limit`, if limited (see `with_time_limit`)
+    fn search(&self, board_state: &mut BoardState, time_limit: Option<Duration>) -> Move {
+        let depth = if self.depth == 0 { 1 } else { self.depth };
+        match time_limit {
+            Some(time_limit) => {
+                let deadline = Instant::now() + time_limit;
+                let mut best_move = get_best_move(board_state, 1, None)
+                    .expect("A search without a deadline should finish");
+                for depth in 2..=depth {
+                    match get_best_move(board_state, depth, Some(deadline)) {
+                        Some(m) => best_move = m,
+                        None => break,
+                    }
+                }
+                best_move
+            }
+            None => get_best_move(board_state, depth, None)
+                .expect("A search without a deadline should finish"),
+        }
     }
 }
 
 impl Player for AiPlayer {
-    fn get_move(&self, board_state: &mut BoardState) -> std::io::Result<Move> {
-        Ok(get_best_move(board_state, self.depth))
+    /// Note: when playing on a clock, the search is also limited to the player's share of the
+    /// time they have left (see `get_time_budget`)
+    fn get_move(
+        &self,
+        board_state: &mut BoardState,
+        clock: Option<&Clock>,
+    ) -> std::io::Result<MoveSignal> {
+        let player = board_state.get_next_player();
+        let time_limit = match (self.time_limit, clock) {
+            (Some(time_limit), Some(clock)) => Some(time_limit.min(get_time_budget(clock, player))),
+            (None, Some(clock)) => Some(get_time_budget(clock, player)),
+            (time_limit, None) => time_limit,
+        };
+        Ok(MoveSignal::Make(self.search(board_state, time_limit)))
+    }
+    /// Note: accepts unless it is ahead on material
+    fn accept_draw(&self, board_state: &mut BoardState) -> std::io::Result<bool> {
+        // the player offering the draw is the one to move
+        let advantage = heuristic(board_state);
+        Ok(match !board_state.get_next_player() {
+            White => advantage <= 0.0,
+            Black => advantage >= 0.0,
+        })
     }
     fn get_display(&self) -> Box<dyn Display> {
         Box::new(NoDisplay {})
     }
+    fn get_name(&self) -> String {
+        format!("AI (depth {})", self.depth)
+    }
+}
+
+/// Return the time to spend on the player's next move: an even share of the time they have left
+/// before more is added (assuming 40 moves when none will be), plus any delay or increment, but
+/// never more than half of the time they have left
+fn get_time_budget(clock: &Clock, player: Colour) -> Duration {
+    let remaining = clock.get_remaining(player);
+    let moves_to_go = clock.get_moves_to_go(player).unwrap_or(40);
+    let bonus = match clock.get_period(player).delay {
+        Some(Delay::Increment(delay) | Delay::Bronstein(delay) | Delay::Simple(delay)) => delay,
+        None => Duration::ZERO,
+    };
+    (remaining / moves_to_go + bonus).min(remaining / 2)
+}
+
+/// Return 1 for White Win, -1 for Black Win
+/// Otherwise, Return a number in range (-1, 1) estimating who is closer to winning
+#[allow(clippy::just_underscores_and_digits)]
+fn heuristic(board_state: &BoardState) -> f64 {
+    board_iterator().fold(0.0, |result, (&row, &column)| {
+        let row_factor = match row {
+            _1 | _8 => 0.85,
+            _2 | _7 => 0.9,
+            _3 | _6 => 0.95,
+            _4 | _5 => 1.0,
+        };
+        let column_factor = match column {
+            A | H => 0.85,
+            B | G => 0.9,
+            C | F => 0.95,
+            D | E => 1.0,
+        };
+        match board_state.get_board()[row][column] {
+            // Some(piece) => result + piece.get_value(),
+            Some(piece) => result + piece.get_value() * row_factor * column_factor,
+            None => result,
+        }
+    })
 }
 
-/// Note: panics if already in checkmate
-fn get_best_move(board_state: &mut BoardState, depth: u8) -> Move {
+/// Return the best move found searching `depth` moves by each player (less one), or None if
+/// `deadline` passes before the search is finished
+///
+/// Note: panics if there are no legal moves (i.e. in checkmate or stalemate)
+fn get_best_move(
+    board_state: &mut BoardState,
+    depth: u8,
+    deadline: Option<Instant>,
+) -> Option<Move> {
     fn rec_helper(
         state: &mut BoardState,
-        depth: u8,
+        depth: u32,
         best_white: f64,
         best_black: f64,
-    ) -> (Option<Move>, f64) {
+        deadline: Option<Instant>,
+    ) -> Option<(Option<Move>, f64)> {
         if depth == 0 {
-            return (None, heuristic(state));
+            return Some((None, heuristic(state)));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return None;
         }
         let current_player = state.get_next_player();
         let (mut current_best, other_best, worst) = match current_player {
@@ -61,30 +184,39 @@ fn get_best_move(board_state: &mut BoardState, depth: u8) -> Move {
                 .get_move_result(m, current_player)
                 .expect("A legal move should be legal");
             state.do_move(record);
+            let (best_white, best_black) = match current_player {
+                White => (current_best, other_best),
+                Black => (other_best, current_best),
+            };
+            let value = match rec_helper(state, depth - 1, best_white, best_black, deadline) {
+                Some((_, value)) => value,
+                None => {
+                    state.undo_move();
+                    return None;
+                }
+            };
             match current_player {
                 White => {
-                    let (_, value) = rec_helper(state, depth - 1, current_best, other_best);
                     if value >= result.1 {
                         result.0 = Some(m);
                         result.1 = value;
                     }
                     if value >= other_best {
                         state.undo_move();
-                        return result;
+                        return Some(result);
                     }
                     if value > current_best {
                         current_best = value;
                     }
                 }
                 Black => {
-                    let (_, value) = rec_helper(state, depth - 1, other_best, current_best);
                     if value <= result.1 {
                         result.0 = Some(m);
                         result.1 = value;
                     }
                     if value <= other_best {
                         state.undo_move();
-                        return result;
+                        return Some(result);
                     }
                     if value < current_best {
                         current_best = value;
@@ -93,37 +225,42 @@ fn get_best_move(board_state: &mut BoardState, depth: u8) -> Move {
             }
             state.undo_move();
         }
-        result
+        Some(result)
     }
-    /// Return 1 for White Win, -1 for Black Win
-    /// Otherwise, Return a number in range (-1, 1) estimating who is closer to winning
-    fn heuristic(board_state: &BoardState) -> f64 {
-        board_iterator().fold(0.0, |result, (&row, &column)| {
-            let row_factor = match row {
-                _1 | _8 => 0.85,
-                _2 | _7 => 0.9,
-                _3 | _6 => 0.95,
-                _4 | _5 => 1.0,
-            };
-            let column_factor = match column {
-                A | H => 0.85,
-                B | G => 0.9,
-                C | F => 0.95,
-                D | E => 1.0,
-            };
-            match board_state.board[row][column] {
-                // Some(piece) => result + piece.get_value(),
-                Some(piece) => result + piece.get_value() * row_factor * column_factor,
-                None => result,
-            }
-        })
-    }
-    let depth = if depth == 0 { 1 } else { depth };
-    let (m, _) = rec_helper(board_state, depth * 2 - 1, -1.0, 1.0);
-    m.unwrap_or_else(|| {
+    let (m, _) = rec_helper(board_state, depth as u32 * 2 - 1, -1.0, 1.0, deadline)?;
+    Some(m.unwrap_or_else(|| {
         panic!(
-            "Cannot use AI to determine next move after Checkmate {:#?}",
+            "Cannot use AI to determine next move when none are legal {:#?}",
             board_state.get_legal_moves(board_state.get_next_player())
         );
-    })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AiPlayer;
+    use crate::board::BoardState;
+    use std::{
+        str::FromStr,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn finds_mate_in_one() {
+        let mut board_state = BoardState::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let m = AiPlayer::new(2).choose_move(&mut board_state);
+        assert_eq!(board_state.to_san(m).unwrap(), "Ra8#");
+    }
+
+    #[test]
+    fn time_limit_stops_the_search() {
+        let mut board_state = BoardState::default();
+        let start = Instant::now();
+        let m = AiPlayer::new(20)
+            .with_time_limit(Duration::from_millis(50))
+            .choose_move(&mut board_state);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(board_state.is_legal_move(m).is_ok());
+        assert_eq!(board_state, BoardState::default());
+    }
 }
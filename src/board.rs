@@ -1,4 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
 
 pub mod piece;
 use piece::*;
@@ -11,7 +15,8 @@ use grid::*;
 
 pub mod moves;
 use moves::{
-    CastlingAvailability,
+    CastleDirection::{self, *},
+    CastlingRights, MoveError,
     MoveRecord::{self, *},
     MoveRecords,
 };
@@ -19,20 +24,113 @@ use moves::{
 use ColumnIndex::*;
 use RowIndex::*;
 
-mod fen;
+pub mod bitboard;
+use bitboard::{bit, square_index, squares, Bitboards};
+
+pub mod outcome;
+use outcome::{DrawReason, GameOutcome, WinReason};
+
+mod zobrist;
+use zobrist::ZOBRIST_KEYS;
+
+pub mod chess960;
+pub mod fen;
+mod movegen;
+pub mod perft;
+pub mod san;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct BoardState {
-    pub current_player: Colour,
-    pub board: Board,
-    pub moves: MoveRecords,
-    pub en_passant_availability: Option<Coordinate>,
+    /// Note: the private fields are changed only by do_move and undo_move (or set up from FEN),
+    /// which keep them in step with each other and the bitboards (see `get_board`,
+    /// `get_next_player` etc. for reading them)
+    current_player: Colour,
+    board: Board,
+    moves: MoveRecords,
+    en_passant_availability: Option<Coordinate>,
+    /// The number of the full move, starting at 1 and incremented after Black's move
+    pub fullmove_number: u32,
+    castling_rights: CastlingRights,
+    /// true iff this is a Chess960 (Fischer Random) game, where castling is given as the King
+    /// taking its own Rook
+    pub chess960: bool,
+    /// Zobrist hash of the position, updated incrementally by do_move and undo_move
+    hash_key: u64,
+    /// The squares of each piece on the board, updated with it by do_move and undo_move
+    bitboards: Bitboards,
+}
+
+/// The reason that a position could not be played from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PositionError {
+    WrongNumberOfKings {
+        colour: Colour,
+        count: u32,
+    },
+    /// the player who has just moved being in check
+    OpponentInCheck(Colour),
+    PawnOnBackRank(Coordinate),
+    /// the index of a Chess960 starting position that does not exist
+    InvalidChess960Index(u16),
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionError::WrongNumberOfKings { colour, count } => {
+                write!(f, "{:?} must have exactly one King (not {})", colour, count)
+            }
+            PositionError::OpponentInCheck(colour) => write!(
+                f,
+                "{:?} cannot be in check when it is not their move",
+                colour
+            ),
+            PositionError::PawnOnBackRank(at) => {
+                write!(f, "Pawns cannot be on the first or last rank ({})", at)
+            }
+            PositionError::InvalidChess960Index(index) => write!(
+                f,
+                "Invalid Chess960 position {} (must be less than {})",
+                index,
+                chess960::NUM_CHESS960_POSITIONS
+            ),
+        }
+    }
 }
 
+impl Error for PositionError {}
+
 use Colour::*;
 use PieceType::*;
 
 impl BoardState {
+    /// Return an error if the position could not have arisen in a game, so cannot be played from
+    ///
+    /// Note: positions parsed from FEN are not checked, since it is also used for test positions.
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for &colour in [White, Black].iter() {
+            let count = self.bitboards.pieces(King, colour).count_ones();
+            if count != 1 {
+                return Err(PositionError::WrongNumberOfKings { colour, count });
+            }
+        }
+        for (&row, &column) in board_iterator() {
+            if let Some(Piece {
+                piece_type: Pawn, ..
+            }) = self.board[row][column]
+            {
+                if row == _1 || row == _8 {
+                    return Err(PositionError::PawnOnBackRank(Coordinate { row, column }));
+                }
+            }
+        }
+        let opponent = !self.get_next_player();
+        if self.is_in_check(opponent) {
+            return Err(PositionError::OpponentInCheck(opponent));
+        }
+        Ok(())
+    }
+
     // pub fn from_array(array: [[char; 8]; 8], player: Colour) -> Result<BoardState, String> {
     //     Ok(BoardState {
     //         board: EnumMap::from_array(
@@ -64,12 +162,24 @@ impl BoardState {
     // }
 
     pub fn new() -> BoardState {
-        BoardState {
+        let mut board_state = BoardState {
             current_player: White,
             board: Board::new(),
-            moves: MoveRecords::new(None),
+            moves: MoveRecords::new(None, 0),
             en_passant_availability: None,
-        }
+            fullmove_number: 1,
+            castling_rights: CastlingRights::standard(),
+            chess960: false,
+            hash_key: 0,
+            bitboards: Bitboards::from_board(&Board::new()),
+        };
+        board_state.hash_key = board_state.compute_hash_key();
+        board_state
+    }
+
+    /// Return the piece on each square, indexed by row then column
+    pub fn get_board(&self) -> &Board {
+        &self.board
     }
 
     pub fn get_next_player(&self) -> Colour {
@@ -80,26 +190,91 @@ impl BoardState {
         !self.current_player
     }
 
-    pub fn get_move_result(&self, m: Move, by: Colour) -> Result<MoveRecord, String> {
+    /// Return the number of halfmoves since the last capture or Pawn advance
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.moves.get_halfmove_clock()
+    }
+
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Return the moves made to reach this position (since the position it was set up from)
+    pub fn get_move_records(&self) -> &[MoveRecord] {
+        self.moves.get_records()
+    }
+
+    /// Return the Rooks each player may still castle with
+    pub fn get_castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    /// Return the square a Pawn could be captured on en passant, if any
+    pub fn get_en_passant_availability(&self) -> Option<Coordinate> {
+        self.en_passant_availability
+    }
+
+    /// Return the Zobrist hash of the position
+    ///
+    /// This covers the pieces, the next player, the castling rights and the en-passant file
+    /// (only when the next player can legally take en-passant), so that positions with equal
+    /// keys are the same position under the repetition rules.
+    pub fn hash_key(&self) -> u64 {
+        self.hash_key
+    }
+
+    /// Compute the Zobrist hash of the position from scratch (see `hash_key`)
+    pub fn compute_hash_key(&self) -> u64 {
+        let pieces_key =
+            board_iterator().fold(0, |key, (&row, &column)| match self.board[row][column] {
+                Some(piece) => key ^ ZOBRIST_KEYS.piece(piece, Coordinate { row, column }),
+                None => key,
+            });
+        let player_key = match self.current_player {
+            White => 0,
+            Black => ZOBRIST_KEYS.black_to_move(),
+        };
+        pieces_key ^ player_key ^ self.get_rights_hash()
+    }
+
+    pub fn get_move_result(&self, m: Move, by: Colour) -> Result<MoveRecord, MoveError> {
         let piece = match self.board[m.from.row][m.from.column] {
             Some(p) => p,
             None => {
-                return Err(String::from("Cannot move an empty square"));
+                return Err(MoveError::EmptySquare);
             }
         };
 
         if piece.colour != by {
-            return Err(String::from("Cannot move opponent's piece"));
+            return Err(MoveError::OpponentsPiece);
         }
 
         if m.from == m.to {
-            return Err(String::from("Must move piece"));
+            return Err(MoveError::NotMoved);
         }
 
+        if piece.piece_type == King {
+            if let Some(direction) = self.get_castle_direction(m, by) {
+                return self.get_castle_result(m, by, direction);
+            }
+        }
+
+        let is_promotion = piece.piece_type == Pawn && m.to.row == (!by).home_rank();
+        if m.promotion.is_some() && !is_promotion {
+            return Err(MoveError::PromotionNotAllowed);
+        }
+        let promote = |taken| match m.promotion {
+            Some(to @ (Rook | Knight | Bishop | Queen)) => {
+                Ok(MoveRecord::PawnPromotion { m, to, taken })
+            }
+            Some(to) => Err(MoveError::InvalidPromotion(to)),
+            None => Err(MoveError::PromotionRequired),
+        };
+
         let destination_square = self.board[m.to.row][m.to.column];
         match destination_square {
             Some(t) if t.colour == by => {
-                return Err(String::from("Cannot take own piece"));
+                return Err(MoveError::TakeOwnPiece);
             }
             _ => {}
         }
@@ -114,13 +289,8 @@ impl BoardState {
             }),
             None => Ok(MoveRecord::SimpleMove { m, first_move }),
         };
-        let check_path_and_return_move_record = |m| match has_no_pieces_between(&self.board, &m) {
-            Ok(()) => return_move_record(),
-            Err((p, c)) => Err(format!(
-                "Cannot move here: blocked at ({:?}, {:?}), by {:?}",
-                c.row, c.column, p
-            )),
-        };
+        let check_path_and_return_move_record =
+            |m| has_no_pieces_between(&self.board, &m).and_then(|()| return_move_record());
 
         let d_row = (m.to.row as i8) - (m.from.row as i8);
         let d_column = (m.to.column as i8) - (m.from.column as i8);
@@ -135,11 +305,7 @@ impl BoardState {
                         None => match d_row * if by == White { -1 } else { 1 } {
                             1 => {
                                 if m.to.row == end_row {
-                                    Ok(MoveRecord::PawnPromotion {
-                                        m,
-                                        to: Queen, // TODO
-                                        taken: None,
-                                    })
+                                    promote(None)
                                 } else {
                                     return_move_record()
                                 }
@@ -150,27 +316,23 @@ impl BoardState {
                                     if self.board[in_between][m.from.column].is_none() {
                                         return_move_record()
                                     } else {
-                                        Err(String::from("Cannot jump with Pawn"))
+                                        Err(MoveError::PawnJump)
                                     }
                                 } else {
-                                    Err(String::from("Cannot double move Pawn unless on start row"))
+                                    Err(MoveError::PawnDoubleMoveNotOnStartRow)
                                 }
                             }
-                            3..=7 => Err(String::from("Cannot move Pawn more than two squares")),
-                            _ => Err(String::from("Cannot move Pawn backwards")),
+                            3..=7 => Err(MoveError::PawnTooFar),
+                            _ => Err(MoveError::PawnBackwards),
                         },
-                        Some(_) => Err(String::from("Cannot take with Pawn unless diagonally")),
+                        Some(_) => Err(MoveError::PawnTakeNotDiagonal),
                     },
                     1 | -1 => match d_row * row_increment {
-                        0 => Err(String::from("Cannot move Pawn horizontally")),
+                        0 => Err(MoveError::PawnHorizontal),
                         1 => match destination_square {
                             Some(taken_piece) => {
                                 if m.to.row == end_row {
-                                    Ok(MoveRecord::PawnPromotion {
-                                        m,
-                                        to: Queen, // TODO
-                                        taken: Some(taken_piece),
-                                    })
+                                    promote(Some(taken_piece))
                                 } else {
                                     return_move_record()
                                 }
@@ -188,107 +350,163 @@ impl BoardState {
                                     first_move,
                                 })
                             }
-                            None => Err(String::from("Cannot move Pawn diagonally unless taking")),
+                            None => Err(MoveError::PawnDiagonalWithoutTaking),
                         },
-                        2..=6 => Err(String::from("Cannot move Pawn here: too far")),
-                        _ => Err(String::from("Cannot move Pawn here: must move forwards")),
+                        2..=6 => Err(MoveError::PawnDiagonalTooFar),
+                        _ => Err(MoveError::PawnDiagonalBackwards),
                     },
                     _ => match d_row {
-                        0 => Err(String::from("Cannot move Pawn horizontally")),
-                        _ => Err(String::from("Cannot move Pawn this way")),
+                        0 => Err(MoveError::PawnHorizontal),
+                        _ => Err(MoveError::PawnInvalidMove),
                     },
                 }
             }
             Rook => match (d_row, d_column) {
                 (_, 0) | (0, _) => check_path_and_return_move_record(m),
-                _ => Err(String::from("Cannot move Rook here: not a straight line")),
+                _ => Err(MoveError::InvalidPattern(Rook)),
             },
             Knight => match (d_row.abs(), d_column.abs()) {
                 (1, 2) => return_move_record(),
                 (2, 1) => return_move_record(),
-                _ => Err(String::from("Cannot move Knight here: not in L pattern")),
+                _ => Err(MoveError::InvalidPattern(Knight)),
             },
             Bishop => match (d_row.abs(), d_column.abs()) {
                 (r, c) if r == c => check_path_and_return_move_record(m),
-                _ => Err(String::from("Cannot move Bishop here: not a diagonal line")),
+                _ => Err(MoveError::InvalidPattern(Bishop)),
             },
             Queen => match (d_row.abs(), d_column.abs()) {
                 (_, 0) | (0, _) => check_path_and_return_move_record(m),
                 (r, c) if r == c => check_path_and_return_move_record(m),
-                _ => Err(String::from("Cannot move Queen here: not in a line")),
+                _ => Err(MoveError::InvalidPattern(Queen)),
             },
             King => match (d_row, d_column) {
                 (-1 | 1, -1 | 1) => return_move_record(), // Diagonal
                 (-1 | 1, 0) => return_move_record(),      // Vertical
                 (0, -1 | 1) => return_move_record(),      // Horizontal
-                // TODO (Fischer Random support): use Move enum instead of difference by 2
-                (0, direction @ (-2 | 2)) => {
-                    // Castle
-                    // check king has not moved
-                    if piece.has_moved {
-                        return Err(String::from("Cannot castle: King has been moved"));
-                    }
-                    // find target rook
-                    let rook_coordinates = Coordinate {
-                        row: m.from.row,
-                        column: if direction == 2 { H } else { A },
-                    };
-                    let rook = match self.board[rook_coordinates.row][rook_coordinates.column] {
-                        Some(piece) => piece,
-                        None => {
-                            return Err(String::from("Cannot castle: no Rook found"));
-                        }
-                    };
-                    // check target rook has not moved
-                    if rook.has_moved {
-                        return Err(String::from(
-                            "Cannot castle this way: target Rook has been moved",
-                        ));
-                    }
-                    // check that castling does not take a piece (TODO: redundant, except for Fischer?)
-                    if let Some(_) = destination_square {
-                        return Err(String::from(
-                            "Cannot castle: cannot take piece during castle",
-                        ));
-                    }
-                    // check_path king -> rook
-                    match has_no_pieces_between(&self.board, &m) {
-                        Ok(()) => {
-                            let rook_move = Move {
-                                from: rook_coordinates,
-                                to: Coordinate {
-                                    row: rook_coordinates.row,
-                                    column: if direction == 2 { F } else { D },
-                                },
-                            };
-                            Ok(MoveRecord::CastleMove {
-                                king_move: m,
-                                rook_move,
-                            })
-                        }
-                        Err((p, c)) => Err(format!(
-                            "Cannot move here: blocked at ({:?}, {:?}), by {:?}",
-                            c.row, c.column, p
-                        )),
-                    }
-                    // TODO: check in-through-out-of-check
-                }
-                _ => Err(String::from("Cannot move King more than one square")),
+                _ => Err(MoveError::InvalidPattern(King)),
+            },
+        }
+    }
+
+    /// Return the direction of the castle that the King move `m` asks for, if it is one
+    ///
+    /// A castle is given as the King taking its own Rook in Chess960, or otherwise as the King
+    /// moving two squares towards the Rook.
+    fn get_castle_direction(&self, m: Move, by: Colour) -> Option<CastleDirection> {
+        let direction = if m.to.column > m.from.column {
+            UpFile
+        } else {
+            DownFile
+        };
+        if m.from.row != m.to.row {
+            None
+        } else if self.chess960 {
+            match self.board[m.to.row][m.to.column] {
+                Some(Piece {
+                    piece_type: Rook,
+                    colour,
+                    ..
+                }) if colour == by => Some(direction),
+                _ => None,
+            }
+        } else {
+            match (m.to.column as i8 - m.from.column as i8).abs() {
+                2 => Some(direction),
+                _ => None,
+            }
+        }
+    }
+
+    fn get_castle_result(
+        &self,
+        m: Move,
+        by: Colour,
+        direction: CastleDirection,
+    ) -> Result<MoveRecord, MoveError> {
+        let rank = by.home_rank();
+        if m.from.row != rank {
+            return Err(MoveError::CastleKingMoved);
+        }
+        // check castling rights in this direction
+        let rook_column = match self.castling_rights.get(by, direction) {
+            Some(column) => column,
+            None => {
+                return Err(MoveError::CastleRightsLost);
+            }
+        };
+        // find target rook
+        let rook_from = Coordinate {
+            row: rank,
+            column: rook_column,
+        };
+        match self.board[rook_from.row][rook_from.column] {
+            Some(Piece {
+                piece_type: Rook,
+                colour,
+                ..
+            }) if colour == by => {}
+            _ => {
+                return Err(MoveError::CastleNoRook);
+            }
+        };
+        if self.chess960 && m.to != rook_from {
+            return Err(MoveError::CastleMustTakeRook);
+        }
+        // the King and Rook always end up on the same files, as in standard chess
+        let (king_column, rook_column) = match direction {
+            UpFile => (G, F),
+            DownFile => (C, D),
+        };
+        let king_move = Move::new(
+            m.from,
+            Coordinate {
+                row: rank,
+                column: king_column,
             },
+        );
+        let rook_move = Move::new(
+            rook_from,
+            Coordinate {
+                row: rank,
+                column: rook_column,
+            },
+        );
+        // every square passed over or landed on must be empty, apart from the King and Rook
+        for path in [king_move, rook_move].iter() {
+            for square in path.squares_between().into_iter().chain(Some(path.to)) {
+                if square == king_move.from || square == rook_move.from {
+                    continue;
+                }
+                if let Some(piece) = self.board[square.row][square.column] {
+                    return Err(MoveError::Blocked { piece, at: square });
+                }
+            }
         }
+        Ok(MoveRecord::CastleMove {
+            king_move,
+            rook_move,
+        })
     }
 
-    pub fn try_move(&mut self, m: Move) -> Result<(), String> {
+    pub fn try_move(&mut self, m: Move) -> Result<(), MoveError> {
         let current_player = self.get_next_player();
         let record = self.get_move_result(m, current_player)?;
         if self.would_be_check(&record, current_player) {
-            Err(String::from("Cannot move here: Check"))
+            Err(MoveError::Check)
         } else {
-            Ok(self.do_move(record))
+            self.do_move(record);
+            Ok(())
         }
     }
 
     pub fn do_move(&mut self, record: MoveRecord) {
+        let is_reversible = self.is_reversible(&record);
+        let piece_changes = self.get_piece_changes(&record);
+        let pieces_hash = get_pieces_hash(&piece_changes);
+        let rights_hash = self.get_rights_hash();
+        let hash_key = self.hash_key;
+        let castling_rights = self.castling_rights;
+        self.update_castling_rights(&record);
         match record {
             SimpleMove { m, .. } => {
                 self.board[m.to.row][m.to.column] =
@@ -303,14 +521,11 @@ impl BoardState {
                 rook_move,
                 king_move,
             } => {
-                self.board[rook_move.to.row][rook_move.to.column] = self.board[rook_move.from.row]
-                    [rook_move.from.column]
-                    .take()
-                    .moved(true);
-                self.board[king_move.to.row][king_move.to.column] = self.board[king_move.from.row]
-                    [king_move.from.column]
-                    .take()
-                    .moved(true);
+                // take both pieces first, since in Chess960 they may land on each other's squares
+                let rook = self.board[rook_move.from.row][rook_move.from.column].take();
+                let king = self.board[king_move.from.row][king_move.from.column].take();
+                self.board[rook_move.to.row][rook_move.to.column] = rook.moved(true);
+                self.board[king_move.to.row][king_move.to.column] = king.moved(true);
             }
             PawnPromotion { m, to, .. } => {
                 self.board[m.from.row][m.from.column] = None;
@@ -321,17 +536,28 @@ impl BoardState {
                 });
             }
         }
+        for &(piece, at) in piece_changes.iter().flatten() {
+            self.bitboards.toggle(piece, at);
+        }
+        if self.current_player == Black {
+            self.fullmove_number += 1;
+        }
         self.current_player = !self.current_player;
-        self.moves.record_move(record);
+        self.moves
+            .record_move(record, is_reversible, hash_key, castling_rights);
         self.recompute_en_passant_availability();
+        self.hash_key ^=
+            pieces_hash ^ ZOBRIST_KEYS.black_to_move() ^ rights_hash ^ self.get_rights_hash();
     }
 
     /// Note: Panics if self.moves is empty
     pub fn undo_move(&mut self) {
-        let record = self
+        let (record, castling_rights) = self
             .moves
             .pop_last_move()
             .expect("ERROR: Cannot undo moves, since none have been made");
+        let rights_hash = self.get_rights_hash();
+        self.castling_rights = castling_rights;
         match record {
             SimpleMove { m, first_move } => {
                 self.board[m.from.row][m.from.column] =
@@ -351,14 +577,10 @@ impl BoardState {
                 rook_move,
                 king_move,
             } => {
-                self.board[rook_move.from.row][rook_move.from.column] = self.board
-                    [rook_move.to.row][rook_move.to.column]
-                    .take()
-                    .moved(false);
-                self.board[king_move.from.row][king_move.from.column] = self.board
-                    [king_move.to.row][king_move.to.column]
-                    .take()
-                    .moved(false);
+                let rook = self.board[rook_move.to.row][rook_move.to.column].take();
+                let king = self.board[king_move.to.row][king_move.to.column].take();
+                self.board[rook_move.from.row][rook_move.from.column] = rook.moved(false);
+                self.board[king_move.from.row][king_move.from.column] = king.moved(false);
             }
             PawnPromotion { m, taken, .. } => {
                 self.board[m.to.row][m.to.column] = taken;
@@ -370,73 +592,180 @@ impl BoardState {
             }
         }
         self.current_player = !self.current_player;
+        if self.current_player == Black {
+            self.fullmove_number -= 1;
+        }
+        let piece_changes = self.get_piece_changes(&record);
+        for &(piece, at) in piece_changes.iter().flatten() {
+            self.bitboards.toggle(piece, at);
+        }
         self.recompute_en_passant_availability();
+        self.hash_key ^= get_pieces_hash(&piece_changes)
+            ^ ZOBRIST_KEYS.black_to_move()
+            ^ rights_hash
+            ^ self.get_rights_hash();
     }
 
     pub fn is_checkmate(&mut self) -> bool {
         let player = self.get_next_player();
-        self.is_in_check(player) && self.get_legal_moves(player).len() == 0
+        self.is_in_check(player) && !self.has_legal_moves(player)
+    }
+
+    pub fn is_stalemate(&mut self) -> bool {
+        let player = self.get_next_player();
+        !self.is_in_check(player) && !self.has_legal_moves(player)
+    }
+
+    /// Return the outcome of the game if it has ended in this position, otherwise None
+    ///
+    /// Note: only draws that apply automatically are considered (see `get_claimable_draw`)
+    pub fn outcome(&mut self) -> Option<GameOutcome> {
+        let player = self.get_next_player();
+        if !self.has_legal_moves(player) {
+            if self.is_in_check(player) {
+                Some(GameOutcome::Win {
+                    winner: !player,
+                    reason: WinReason::Checkmate,
+                })
+            } else {
+                Some(GameOutcome::Draw(DrawReason::Stalemate))
+            }
+        } else if self.is_insufficient_material() {
+            Some(GameOutcome::Draw(DrawReason::InsufficientMaterial))
+        } else if self.get_repetition_count() >= 5 {
+            Some(GameOutcome::Draw(DrawReason::FivefoldRepetition))
+        } else if self.get_halfmove_clock() >= 150 {
+            Some(GameOutcome::Draw(DrawReason::SeventyFiveMoveRule))
+        } else {
+            None
+        }
+    }
+
+    /// Return the rule under which the next player may claim a draw, if any
+    pub fn get_claimable_draw(&self) -> Option<DrawReason> {
+        if self.get_repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.get_halfmove_clock() >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Return true iff neither player has enough material left to checkmate
+    ///
+    /// This covers King vs King, King and a Knight or Bishop vs King, and Kings with any
+    /// number of Bishops that all stand on squares of the same colour.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        let mut bishop_square_colours = HashSet::new();
+        for (&row, &column) in board_iterator() {
+            match self.board[row][column] {
+                Some(Piece {
+                    piece_type: Pawn | Rook | Queen,
+                    ..
+                }) => return false,
+                Some(Piece {
+                    piece_type: Knight, ..
+                }) => knights += 1,
+                Some(Piece {
+                    piece_type: Bishop, ..
+                }) => {
+                    bishop_square_colours.insert((row as u8 + column as u8) % 2);
+                }
+                Some(Piece {
+                    piece_type: King, ..
+                })
+                | None => {}
+            }
+        }
+        match (knights, bishop_square_colours.len()) {
+            (0, _) => bishop_square_colours.len() <= 1,
+            (1, 0) => true,
+            _ => false,
+        }
+    }
+
+    /// Return true iff the player has more than a King, or a King and a single Knight or Bishop,
+    /// so could win if their opponent ran out of time
+    ///
+    /// Note: this follows the common rule that a lone minor piece cannot win on time, even where
+    /// a checkmate with the help of the opponent's pieces would be possible.
+    pub fn has_mating_material(&self, player: Colour) -> bool {
+        let mut minor_pieces = 0;
+        for (&row, &column) in board_iterator() {
+            match self.board[row][column] {
+                Some(Piece {
+                    piece_type: Pawn | Rook | Queen,
+                    colour,
+                    ..
+                }) if colour == player => return true,
+                Some(Piece {
+                    piece_type: Knight | Bishop,
+                    colour,
+                    ..
+                }) if colour == player => minor_pieces += 1,
+                _ => {}
+            }
+        }
+        minor_pieces > 1
+    }
+
+    /// Return the number of times the current position has occurred, including now
+    pub fn get_repetition_count(&self) -> usize {
+        1 + self.moves.count_repetitions(self.hash_key)
     }
 
     /// Note: will panic if King is not found
     pub fn is_in_check(&self, player: Colour) -> bool {
         let king_coordinates = self
             .find_king(player)
-            .expect(&format!("{:?} King not found {:#?}", player, self.board)[..]);
-        self.has_moves_to(king_coordinates, !player)
+            .unwrap_or_else(|| panic!("{:?} King not found {:#?}", player, self.board));
+        self.bitboards
+            .is_attacked(square_index(king_coordinates), !player)
     }
 
+    /// Note: a Pawn reaching the last rank has one move for each type of promotion
     pub fn get_legal_moves_from(&mut self, from: Coordinate, by: Colour) -> Vec<Move> {
-        match self.board[from.row][from.column] {
-            Some(piece) if piece.colour == by => board_iterator()
-                .filter_map(|(&row, &column)| {
-                    let to = Coordinate { row, column };
-                    let m = Move { from, to };
-                    match self.get_move_result(m, by) {
-                        Ok(record) if !self.would_be_check(&record, by) => Some(m),
-                        _ => None,
-                    }
-                })
-                .collect(),
-            _ => vec![],
-        }
+        self.get_legal_moves(by)
+            .into_iter()
+            .filter(|m| m.from == from)
+            .collect()
     }
 
-    pub fn is_legal_move(&mut self, m: Move) -> Result<(), String> {
+    pub fn is_legal_move(&mut self, m: Move) -> Result<(), MoveError> {
         let player = self.get_next_player();
         self.get_move_result(m, player).and_then(|record| {
             if !self.would_be_check(&record, player) {
                 Ok(())
             } else {
-                Err(String::from("Cannot move here: Check"))
+                Err(MoveError::Check)
             }
         })
     }
 
-    /// Note: Expensive operation
+    /// Note: a Pawn reaching the last rank has one move for each type of promotion
     ///
     /// Examples:
     /// ```
-    /// use chess::{BoardState, ColumnIndex::*, RowIndex::*, Move, Coordinate, Colour::*};
-    /// let mut board_state = BoardState::from_array([
-    ///     ['R', 'N', 'q', '_', 'K', 'B', 'N', 'R'],
-    ///     ['P', 'P', '_', '_', 'P', 'P', 'P', '_'],
-    ///     ['_', '_', 'P', '_', '_', '_', '_', '_'],
-    ///     ['_', 'Q', '_', 'P', '_', '_', '_', '_'],
-    ///     ['_', '_', '_', '_', '_', '_', '_', '_'],
-    ///     ['_', '_', '_', '_', 'p', 'p', 'p', '_'],
-    ///     ['p', '_', 'p', 'p', '_', '_', '_', 'p'],
-    ///     ['r', 'n', 'b', '_', 'k', '_', 'n', 'r'],
-    /// ], Black).unwrap();
-    /// assert_eq!(board_state.get_legal_moves(Black).len(), 0);
+    /// use chess::board::{piece::Colour::*, BoardState};
+    /// use std::str::FromStr;
+    /// let mut board_state = BoardState::from_str(
+    ///     "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+    /// ).unwrap();
+    /// assert_eq!(board_state.get_legal_moves(White).len(), 0);
     /// ```
     pub fn get_legal_moves(&mut self, by: Colour) -> Vec<Move> {
-        board_iterator()
-            .map(|(&row, &column)| self.get_legal_moves_from(Coordinate { row, column }, by))
-            .flatten()
+        self.get_legal_move_records(by)
+            .iter()
+            .map(|record| record.get_move(self.chess960))
             .collect()
     }
 
+    pub fn has_legal_moves(&mut self, by: Colour) -> bool {
+        !self.get_legal_move_records(by).is_empty()
+    }
+
     pub fn get_legal_moves_map(&mut self, by: Colour) -> HashMap<Coordinate, HashSet<Coordinate>> {
         self.get_legal_moves(by)
             .into_iter()
@@ -446,15 +775,141 @@ impl BoardState {
             })
     }
 
-    /// Note: Some of these moves may result in Check
-    fn has_moves_to(&self, to: Coordinate, by: Colour) -> bool {
-        for (&row, &column) in board_iterator() {
-            let from = Coordinate { row, column };
-            if self.get_move_result(Move { from, to }, by).is_ok() {
-                return true;
+    /// Remove the castling rights lost by making the move
+    ///
+    /// REQ: the move has not been made yet
+    fn update_castling_rights(&mut self, record: &MoveRecord) {
+        let (from, to) = match record {
+            SimpleMove { m, .. } | TakeMove { m, .. } | PawnPromotion { m, .. } => (m.from, m.to),
+            CastleMove { king_move, .. } => (king_move.from, king_move.to),
+        };
+        if let Some(Piece {
+            piece_type: King,
+            colour,
+            ..
+        }) = self.board[from.row][from.column]
+        {
+            self.castling_rights.remove_all(colour);
+        }
+        // a Rook moving from, or being taken on, its castling square
+        self.castling_rights.remove_rook_at(from);
+        self.castling_rights.remove_rook_at(to);
+    }
+
+    /// Return true iff the move is neither a capture nor a Pawn move
+    fn is_reversible(&self, record: &MoveRecord) -> bool {
+        match record {
+            SimpleMove { m, .. } => !matches!(
+                self.board[m.from.row][m.from.column],
+                Some(Piece {
+                    piece_type: Pawn,
+                    ..
+                })
+            ),
+            CastleMove { .. } => true,
+            TakeMove { .. } | PawnPromotion { .. } => false,
+        }
+    }
+
+    /// Return each piece that the move adds to or removes from a square
+    ///
+    /// REQ: the move has not been made yet (or has been undone)
+    fn get_piece_changes(&self, record: &MoveRecord) -> PieceChanges {
+        let piece_at = |at: Coordinate| {
+            self.board[at.row][at.column].expect("There should be a piece at the start of a move")
+        };
+        let moved = |m: &Move| {
+            let piece = piece_at(m.from);
+            [Some((piece, m.from)), Some((piece, m.to))]
+        };
+        match record {
+            SimpleMove { m, .. } => {
+                let [from, to] = moved(m);
+                [from, to, None, None]
+            }
+            TakeMove {
+                m,
+                taken,
+                taken_from,
+                ..
+            } => {
+                let [from, to] = moved(m);
+                [from, to, Some((*taken, *taken_from)), None]
+            }
+            CastleMove {
+                rook_move,
+                king_move,
+            } => {
+                let [rook_from, rook_to] = moved(rook_move);
+                let [king_from, king_to] = moved(king_move);
+                [rook_from, rook_to, king_from, king_to]
+            }
+            PawnPromotion { m, to, taken } => {
+                let pawn = piece_at(m.from);
+                [
+                    Some((pawn, m.from)),
+                    Some((Piece::new(*to, pawn.colour), m.to)),
+                    taken.map(|taken| (taken, m.to)),
+                    None,
+                ]
             }
         }
-        false
+    }
+
+    /// Return the combined hash of the castling rights and en-passant file
+    fn get_rights_hash(&self) -> u64 {
+        let castling_hash = [White, Black].iter().fold(0, |key, &colour| {
+            [UpFile, DownFile]
+                .iter()
+                .filter_map(|&direction| self.castling_rights.get(colour, direction))
+                .fold(key, |key, file| key ^ ZOBRIST_KEYS.castling(colour, file))
+        });
+        let en_passant_hash = match self.en_passant_availability {
+            Some(target) if self.can_take_en_passant(target) => {
+                ZOBRIST_KEYS.en_passant(target.column)
+            }
+            _ => 0,
+        };
+        castling_hash ^ en_passant_hash
+    }
+
+    /// Return true iff the next player can legally take en-passant on `target`
+    fn can_take_en_passant(&self, target: Coordinate) -> bool {
+        let player = self.current_player;
+        let row_increment = if player == White { 1 } else { -1 };
+        let row = RowIndex::from((target.row as i8 + row_increment) as usize);
+        let column = target.column as i8;
+        let pawns: Vec<_> = [column - 1, column + 1]
+            .iter()
+            .filter(|&&c| (0..8).contains(&c))
+            .map(|&c| Coordinate {
+                row,
+                column: ColumnIndex::from(c as usize),
+            })
+            .filter(|from| {
+                matches!(
+                    self.board[from.row][from.column],
+                    Some(Piece { piece_type: Pawn, colour, .. }) if colour == player
+                )
+            })
+            .collect();
+        let king = match self.find_king(player) {
+            Some(king) => king,
+            None => return !pawns.is_empty(),
+        };
+        // Make the capture on a copy of the bitboards, since taking en-passant may expose the King
+        let taken_from = Coordinate {
+            row,
+            column: target.column,
+        };
+        pawns.into_iter().any(|from| {
+            let pawn = Piece::new(Pawn, player);
+            let mut bitboards = self.bitboards;
+            bitboards.toggle(pawn, from);
+            bitboards.toggle(pawn, target);
+            bitboards.toggle(Piece::new(Pawn, !player), taken_from);
+            !bitboards.is_attacked(square_index(king), !player)
+        })
     }
 
     fn recompute_en_passant_availability(&mut self) {
@@ -462,36 +917,29 @@ impl BoardState {
     }
 
     fn find_king(&self, player: Colour) -> Option<Coordinate> {
-        board_iterator()
-            .find(|(&r, &c)| match self.board[r][c] {
-                Some(Piece {
-                    piece_type: King,
-                    colour,
-                    ..
-                }) if colour == player => true,
-                _ => false,
-            })
-            .map(|(&row, &column)| Coordinate { row, column })
+        squares(self.bitboards.pieces(King, player)).next()
     }
 
     fn would_be_check(&mut self, record: &MoveRecord, by: Colour) -> bool {
-        if let CastleMove { king_move, .. } = record {
+        if let CastleMove {
+            king_move,
+            rook_move,
+        } = record
+        {
             // cannot castle out of check
             if self.is_in_check(by) {
                 return true;
             }
-            // cannot castle through check
-            for square in king_move.squares_between() {
-                let imaginary_king_move = SimpleMove {
-                    m: Move {
-                        from: king_move.from,
-                        to: square,
-                    },
-                    first_move: true, // this is always true for a valid CastleMove
-                };
-                if self.would_be_check(&imaginary_king_move, by) {
-                    return true;
-                }
+            // cannot castle through check: test each square the King passes over, with the King
+            // and Rook lifted off the board since they move at the same time
+            let occupied = self.bitboards.occupied() & !bit(king_move.from) & !bit(rook_move.from);
+            let through_check = king_move.squares_between().into_iter().any(|square| {
+                self.bitboards
+                    .attackers_to(square_index(square), !by, occupied)
+                    != 0
+            });
+            if through_check {
+                return true;
             }
         }
         self.do_move(*record);
@@ -501,8 +949,386 @@ impl BoardState {
     }
 }
 
+/// The pieces added to or removed from squares by a move
+type PieceChanges = [Option<(Piece, Coordinate)>; 4];
+
+/// Return the combined hash of the pieces that a move adds and removes
+fn get_pieces_hash(piece_changes: &PieceChanges) -> u64 {
+    piece_changes
+        .iter()
+        .flatten()
+        .fold(0, |key, &(piece, at)| key ^ ZOBRIST_KEYS.piece(piece, at))
+}
+
 impl Default for BoardState {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        moves::{CastleDirection::*, CastlingRights, MoveError},
+        outcome::*,
+        BoardState,
+        Colour::*,
+        ColumnIndex::*,
+        Coordinate, Move, Piece,
+        PieceType::*,
+        PositionError,
+        RowIndex::*,
+    };
+    use std::str::FromStr;
+
+    fn play(board_state: &mut BoardState, moves: &[(Coordinate, Coordinate)]) {
+        for &(from, to) in moves {
+            board_state.try_move(Move::new(from, to)).unwrap();
+        }
+    }
+
+    /// Play a deterministic pseudo-random game, checking the hash against a recomputation
+    fn check_hash_through_random_game(board_state: &mut BoardState, plies: usize) {
+        let mut seed: u64 = 0x1234_5678;
+        let mut played = 0;
+        for _ in 0..plies {
+            let player = board_state.get_next_player();
+            let legal_moves = board_state.get_legal_moves(player);
+            if legal_moves.is_empty() {
+                break;
+            }
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            let m = legal_moves[(seed >> 33) as usize % legal_moves.len()];
+            board_state.try_move(m).unwrap();
+            played += 1;
+            assert_eq!(board_state.hash_key(), board_state.compute_hash_key());
+        }
+        for _ in 0..played {
+            board_state.undo_move();
+            assert_eq!(board_state.hash_key(), board_state.compute_hash_key());
+        }
+    }
+
+    #[test]
+    fn hash_key_is_updated_incrementally() {
+        let mut board_state = BoardState::default();
+        let start_key = board_state.hash_key();
+        check_hash_through_random_game(&mut board_state, 60);
+        assert_eq!(board_state.hash_key(), start_key);
+
+        // castling, en-passant and promotions
+        let mut board_state =
+            BoardState::from_str("r3k2r/1P4p1/8/3pP3/8/8/p5P1/R3K2R w KQkq d6 0 1").unwrap();
+        let start_key = board_state.hash_key();
+        check_hash_through_random_game(&mut board_state, 40);
+        assert_eq!(board_state.hash_key(), start_key);
+    }
+
+    #[test]
+    fn hash_key_identifies_positions() {
+        let g1 = Coordinate { row: _1, column: G };
+        let f3 = Coordinate { row: _3, column: F };
+        let b1 = Coordinate { row: _1, column: B };
+        let c3 = Coordinate { row: _3, column: C };
+        let g8 = Coordinate { row: _8, column: G };
+        let f6 = Coordinate { row: _6, column: F };
+        let b8 = Coordinate { row: _8, column: B };
+        let c6 = Coordinate { row: _6, column: C };
+
+        let mut first = BoardState::default();
+        play(&mut first, &[(g1, f3), (g8, f6), (b1, c3), (b8, c6)]);
+        let mut second = BoardState::default();
+        play(&mut second, &[(b1, c3), (b8, c6), (g1, f3), (g8, f6)]);
+        assert_eq!(first.hash_key(), second.hash_key());
+
+        // Same pieces, different player
+        let mut third = BoardState::default();
+        play(&mut third, &[(g1, f3), (g8, f6), (b1, c3)]);
+        let mut fourth = BoardState::default();
+        play(&mut fourth, &[(b1, c3), (g8, f6), (g1, f3)]);
+        assert_eq!(third.hash_key(), fourth.hash_key());
+        assert_ne!(third.hash_key(), BoardState::default().hash_key());
+        play(&mut fourth, &[(b8, c6)]);
+        assert_ne!(third.hash_key(), fourth.hash_key());
+    }
+
+    #[test]
+    fn threefold_and_fivefold_repetition() {
+        let g1 = Coordinate { row: _1, column: G };
+        let f3 = Coordinate { row: _3, column: F };
+        let g8 = Coordinate { row: _8, column: G };
+        let f6 = Coordinate { row: _6, column: F };
+        let shuffle = [(g1, f3), (g8, f6), (f3, g1), (f6, g8)];
+
+        let mut board_state = BoardState::default();
+        play(&mut board_state, &shuffle);
+        assert_eq!(board_state.get_repetition_count(), 2);
+        assert_eq!(board_state.get_claimable_draw(), None);
+        play(&mut board_state, &shuffle);
+        assert_eq!(board_state.get_repetition_count(), 3);
+        assert_eq!(
+            board_state.get_claimable_draw(),
+            Some(DrawReason::ThreefoldRepetition)
+        );
+        assert_eq!(board_state.outcome(), None);
+        play(&mut board_state, &shuffle);
+        play(&mut board_state, &shuffle);
+        assert_eq!(
+            board_state.outcome(),
+            Some(GameOutcome::Draw(DrawReason::FivefoldRepetition))
+        );
+        board_state.undo_move();
+        assert_eq!(board_state.get_repetition_count(), 4);
+        assert_eq!(board_state.outcome(), None);
+    }
+
+    #[test]
+    fn repetition_requires_same_castling_rights() {
+        let g1 = Coordinate { row: _1, column: G };
+        let f3 = Coordinate { row: _3, column: F };
+        let h1 = Coordinate { row: _1, column: H };
+        let g8 = Coordinate { row: _8, column: G };
+        let f6 = Coordinate { row: _6, column: F };
+        let h8 = Coordinate { row: _8, column: H };
+
+        let mut board_state = BoardState::default();
+        play(&mut board_state, &[(g1, f3), (g8, f6)]);
+        let key = board_state.hash_key();
+        play(&mut board_state, &[(h1, g1), (h8, g8), (g1, h1), (g8, h8)]);
+        assert_ne!(board_state.hash_key(), key);
+        assert_eq!(board_state.get_repetition_count(), 1);
+    }
+
+    #[test]
+    fn repetition_requires_same_en_passant_possibilities() {
+        let without_en_passant = BoardState::from_str("8/8/8/KPp4r/8/8/8/7k w - - 0 1").unwrap();
+        // b5xc6 is illegal, since it would expose the King to the Rook
+        let pinned = BoardState::from_str("8/8/8/KPp4r/8/8/8/7k w - c6 0 1").unwrap();
+        assert_eq!(pinned.hash_key(), without_en_passant.hash_key());
+
+        let without_en_passant = BoardState::from_str("8/8/8/1Pp5/8/8/8/K6k w - - 0 1").unwrap();
+        let legal = BoardState::from_str("8/8/8/1Pp5/8/8/8/K6k w - c6 0 1").unwrap();
+        assert_ne!(legal.hash_key(), without_en_passant.hash_key());
+    }
+
+    #[test]
+    fn pawn_promotion_choices() {
+        let mut board_state = BoardState::from_str("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let a7 = Coordinate { row: _7, column: A };
+        let a8 = Coordinate { row: _8, column: A };
+        let b8 = Coordinate { row: _8, column: B };
+        let promotions: Vec<_> = board_state
+            .get_legal_moves_from(a7, White)
+            .into_iter()
+            .map(|m| (m.to, m.promotion))
+            .collect();
+        assert_eq!(promotions.len(), 8);
+        for &to in [a8, b8].iter() {
+            for &piece_type in [Queen, Rook, Bishop, Knight].iter() {
+                assert!(promotions.contains(&(to, Some(piece_type))));
+            }
+        }
+
+        assert_eq!(
+            board_state.try_move(Move::new(a7, a8)),
+            Err(MoveError::PromotionRequired)
+        );
+        assert_eq!(
+            board_state.try_move(Move::new(a7, a8).with_promotion(King)),
+            Err(MoveError::InvalidPromotion(King))
+        );
+        board_state
+            .try_move(Move::new(a7, b8).with_promotion(Knight))
+            .unwrap();
+        assert_eq!(
+            board_state.board[_8][B].map(|piece| piece.piece_type),
+            Some(Knight)
+        );
+        board_state.undo_move();
+        assert_eq!(
+            board_state.board[_7][A].map(|piece| piece.piece_type),
+            Some(Pawn)
+        );
+        assert_eq!(
+            board_state.try_move(
+                Move::new(
+                    Coordinate { row: _1, column: E },
+                    Coordinate { row: _2, column: E }
+                )
+                .with_promotion(Queen)
+            ),
+            Err(MoveError::PromotionNotAllowed)
+        );
+    }
+
+    #[test]
+    fn validate_positions() {
+        let validate = |fen| BoardState::from_str(fen).unwrap().validate();
+        assert_eq!(BoardState::default().validate(), Ok(()));
+        assert_eq!(validate("7k/8/8/8/8/8/Q7/4K3 w - - 0 1"), Ok(()));
+        assert_eq!(
+            validate("8/8/8/8/8/8/8/Q3K3 w - - 0 1"),
+            Err(PositionError::WrongNumberOfKings {
+                colour: Black,
+                count: 0
+            })
+        );
+        assert_eq!(
+            validate("k6k/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(PositionError::WrongNumberOfKings {
+                colour: Black,
+                count: 2
+            })
+        );
+        assert_eq!(
+            validate("P6k/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(PositionError::PawnOnBackRank(Coordinate {
+                row: _8,
+                column: A
+            }))
+        );
+        assert_eq!(
+            validate("7k/8/8/8/8/8/8/q3K3 b - - 0 1"),
+            Err(PositionError::OpponentInCheck(White))
+        );
+    }
+
+    #[test]
+    fn blocked_moves_name_the_blocking_piece() {
+        let mut board_state = BoardState::default();
+        let d2 = Coordinate { row: _2, column: D };
+        assert_eq!(
+            board_state.try_move(Move::new(
+                Coordinate { row: _1, column: D },
+                Coordinate { row: _4, column: D }
+            )),
+            Err(MoveError::Blocked {
+                piece: Piece::new(Pawn, White),
+                at: d2
+            })
+        );
+    }
+
+    #[test]
+    fn castling_rights_follow_do_and_undo() {
+        let mut board_state =
+            BoardState::from_str("r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 0 1").unwrap();
+        let g2 = Coordinate { row: _2, column: G };
+        let h1 = Coordinate { row: _1, column: H };
+        let a1 = Coordinate { row: _1, column: A };
+        let a8 = Coordinate { row: _8, column: A };
+        let e1 = Coordinate { row: _1, column: E };
+        let g1 = Coordinate { row: _1, column: G };
+        let c1 = Coordinate { row: _1, column: C };
+
+        // Rook taken
+        play(&mut board_state, &[(g2, h1)]);
+        assert_eq!(board_state.castling_rights.get(White, UpFile), None);
+        assert_eq!(board_state.castling_rights.get(White, DownFile), Some(A));
+        assert!(board_state.try_move(Move::new(e1, g1)).is_err());
+        // Rook moved
+        play(&mut board_state, &[(a1, a8)]);
+        assert_eq!(board_state.castling_rights.get(White, DownFile), None);
+        assert_eq!(board_state.castling_rights.get(Black, DownFile), None);
+        assert_eq!(board_state.castling_rights.get(Black, UpFile), Some(H));
+        assert_eq!(board_state.to_fen(), "R3k2r/8/8/8/8/8/8/4K2b b k - 0 2");
+
+        board_state.undo_move();
+        board_state.undo_move();
+        assert_eq!(board_state.castling_rights, CastlingRights::standard());
+        assert_eq!(
+            board_state.to_fen(),
+            "r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 0 1"
+        );
+
+        // Path to the Rook must be clear, even where the King does not pass
+        let mut board_state = BoardState::from_str("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        assert!(board_state.try_move(Move::new(e1, c1)).is_err());
+        board_state.try_move(Move::new(e1, g1)).unwrap();
+        assert_eq!(board_state.to_fen(), "4k3/8/8/8/8/8/8/RN3RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn chess960_castling() {
+        let b1 = Coordinate { row: _1, column: B };
+        let c1 = Coordinate { row: _1, column: C };
+        let e1 = Coordinate { row: _1, column: E };
+        let f1 = Coordinate { row: _1, column: F };
+        let g1 = Coordinate { row: _1, column: G };
+        let a1 = Coordinate { row: _1, column: A };
+
+        // King takes Rook, ending on the standard squares
+        let fen = "r3k2r/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1";
+        let mut board_state =
+            BoardState::from_str("r3k2r/8/8/8/8/8/8/1R2K1R1 w GBha - 0 1").unwrap();
+        assert!(board_state.chess960);
+        assert_eq!(board_state.to_fen(), fen);
+        assert!(board_state.try_move(Move::new(e1, c1)).is_err());
+        board_state.try_move(Move::new(e1, g1)).unwrap();
+        assert_eq!(board_state.to_fen(), "r3k2r/8/8/8/8/8/8/1R3RK1 b kq - 1 1");
+        assert_eq!(board_state.hash_key(), board_state.compute_hash_key());
+        board_state.undo_move();
+        assert_eq!(board_state.to_fen(), fen);
+        board_state.try_move(Move::new(e1, b1)).unwrap();
+        assert_eq!(board_state.to_fen(), "r3k2r/8/8/8/8/8/8/2KR2R1 b kq - 1 1");
+        assert_eq!(board_state.hash_key(), board_state.compute_hash_key());
+
+        // King and Rook swap squares
+        let mut board_state = BoardState::from_str("4k3/8/8/8/8/8/8/5KR1 w G - 0 1").unwrap();
+        board_state.try_move(Move::new(f1, g1)).unwrap();
+        assert_eq!(board_state.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        board_state.undo_move();
+        assert_eq!(board_state.to_fen(), "4k3/8/8/8/8/8/8/5KR1 w K - 0 1");
+
+        // King moves onto a square next to it, with the Rook jumping over
+        let mut board_state = BoardState::from_str("4k3/8/8/8/8/8/8/RK6 w A - 0 1").unwrap();
+        assert!(board_state.chess960);
+        board_state.try_move(Move::new(b1, a1)).unwrap();
+        assert_eq!(board_state.to_fen(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+
+        // cannot castle through check
+        let mut board_state = BoardState::from_str("k4r2/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(board_state.try_move(Move::new(e1, g1)).is_err());
+        board_state.try_move(Move::new(e1, b1)).unwrap();
+    }
+
+    #[test]
+    fn move_counters_follow_do_and_undo() {
+        let mut board_state = BoardState::default();
+        let e2 = Coordinate { row: _2, column: E };
+        let e4 = Coordinate { row: _4, column: E };
+        let g8 = Coordinate { row: _8, column: G };
+        let f6 = Coordinate { row: _6, column: F };
+        let g1 = Coordinate { row: _1, column: G };
+        let f3 = Coordinate { row: _3, column: F };
+        play(&mut board_state, &[(e2, e4), (g8, f6), (g1, f3)]);
+        assert_eq!(board_state.get_halfmove_clock(), 2);
+        assert_eq!(board_state.get_fullmove_number(), 2);
+        assert_eq!(board_state.get_next_player(), Black);
+        board_state.undo_move();
+        board_state.undo_move();
+        assert_eq!(board_state.get_halfmove_clock(), 0);
+        assert_eq!(board_state.get_fullmove_number(), 1);
+    }
+
+    #[test]
+    fn fifty_and_seventy_five_move_rules() {
+        let mut board_state = BoardState::from_str("8/8/4k3/8/8/4K3/8/7R w - - 99 80").unwrap();
+        assert_eq!(board_state.get_claimable_draw(), None);
+        let h1 = Coordinate { row: _1, column: H };
+        let h2 = Coordinate { row: _2, column: H };
+        play(&mut board_state, &[(h1, h2)]);
+        assert_eq!(
+            board_state.get_claimable_draw(),
+            Some(DrawReason::FiftyMoveRule)
+        );
+        assert_eq!(board_state.outcome(), None);
+
+        let mut board_state = BoardState::from_str("8/8/4k3/8/8/4K3/8/7R w - - 149 80").unwrap();
+        play(&mut board_state, &[(h1, h2)]);
+        assert_eq!(
+            board_state.outcome(),
+            Some(GameOutcome::Draw(DrawReason::SeventyFiveMoveRule))
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use super::{
+    coordinates::{ColumnIndex, Coordinate, RowIndex},
+    grid::{board_iterator, Board},
+    piece::{
+        Colour::{self, *},
+        Piece,
+        PieceType::{self, *},
+    },
+};
+
+/// A set of squares, with bit `8 * row + column` set for each square in the set (so bit 0 is A8
+/// and bit 63 is H1)
+pub type Bitboard = u64;
+
+/// Return the index of the bit for `at`
+pub fn square_index(at: Coordinate) -> usize {
+    at.row as usize * 8 + at.column as usize
+}
+
+/// Return the Bitboard containing just `at`
+pub fn bit(at: Coordinate) -> Bitboard {
+    1 << square_index(at)
+}
+
+/// Return the square with the given bit index
+pub fn coordinate(index: usize) -> Coordinate {
+    Coordinate {
+        row: RowIndex::from(index / 8),
+        column: ColumnIndex::from(index % 8),
+    }
+}
+
+/// Iterate over the squares in `bitboard`, from A8 to H1
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Coordinate> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            None
+        } else {
+            let index = bitboard.trailing_zeros() as usize;
+            bitboard &= bitboard - 1;
+            Some(coordinate(index))
+        }
+    })
+}
+
+/// Precomputed attacks and lines for each square
+struct AttackTables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    /// pawn captures, indexed by colour
+    pawn: [[Bitboard; 64]; 2],
+    /// the lines through each square (not including it): file, rank, diagonal, anti-diagonal
+    lines: [[Bitboard; 4]; 64],
+}
+
+/// Return the set of squares reached from `square` by each (row, column) step that stays on the
+/// board
+const fn leaper_attacks(square: usize, steps: &[(i8, i8)]) -> Bitboard {
+    let row = (square / 8) as i8;
+    let column = (square % 8) as i8;
+    let mut attacks = 0;
+    let mut i = 0;
+    while i < steps.len() {
+        let (to_row, to_column) = (row + steps[i].0, column + steps[i].1);
+        if to_row >= 0 && to_row < 8 && to_column >= 0 && to_column < 8 {
+            attacks |= 1 << (to_row * 8 + to_column);
+        }
+        i += 1;
+    }
+    attacks
+}
+
+/// Return the squares on the line through `square` in direction (row, column), in both senses,
+/// not including `square` itself
+const fn line(square: usize, direction: (i8, i8)) -> Bitboard {
+    let mut line = 0;
+    let mut sense = -1;
+    while sense <= 1 {
+        let mut row = (square / 8) as i8 + direction.0 * sense;
+        let mut column = (square % 8) as i8 + direction.1 * sense;
+        while row >= 0 && row < 8 && column >= 0 && column < 8 {
+            line |= 1 << (row * 8 + column);
+            row += direction.0 * sense;
+            column += direction.1 * sense;
+        }
+        sense += 2;
+    }
+    line
+}
+
+impl AttackTables {
+    const fn new() -> Self {
+        const KNIGHT_STEPS: [(i8, i8); 8] = [
+            (-2, -1),
+            (-2, 1),
+            (-1, -2),
+            (-1, 2),
+            (1, -2),
+            (1, 2),
+            (2, -1),
+            (2, 1),
+        ];
+        const KING_STEPS: [(i8, i8); 8] = [
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, -1),
+            (0, 1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+        ];
+        // rows count down the board, so White Pawns attack towards row 0
+        const BLACK_PAWN_STEPS: [(i8, i8); 2] = [(1, -1), (1, 1)];
+        const WHITE_PAWN_STEPS: [(i8, i8); 2] = [(-1, -1), (-1, 1)];
+        let mut tables = AttackTables {
+            knight: [0; 64],
+            king: [0; 64],
+            pawn: [[0; 64]; 2],
+            lines: [[0; 4]; 64],
+        };
+        let mut square = 0;
+        while square < 64 {
+            tables.knight[square] = leaper_attacks(square, &KNIGHT_STEPS);
+            tables.king[square] = leaper_attacks(square, &KING_STEPS);
+            tables.pawn[Black as usize][square] = leaper_attacks(square, &BLACK_PAWN_STEPS);
+            tables.pawn[White as usize][square] = leaper_attacks(square, &WHITE_PAWN_STEPS);
+            tables.lines[square] = [
+                line(square, (1, 0)),
+                line(square, (0, 1)),
+                line(square, (1, 1)),
+                line(square, (1, -1)),
+            ];
+            square += 1;
+        }
+        tables
+    }
+}
+
+static ATTACK_TABLES: AttackTables = AttackTables::new();
+
+/// Return the squares attacked along `line` by a slider on `square`, which stop at (and include)
+/// the first occupied square in each sense, using the hyperbola quintessence
+fn line_attacks(square: usize, line: Bitboard, occupied: Bitboard) -> Bitboard {
+    let slider: Bitboard = 1 << square;
+    let occupied = occupied & line;
+    let forward = occupied.wrapping_sub(slider.wrapping_mul(2));
+    let reverse = occupied
+        .reverse_bits()
+        .wrapping_sub(slider.reverse_bits().wrapping_mul(2))
+        .reverse_bits();
+    (forward ^ reverse) & line
+}
+
+pub fn knight_attacks(square: usize) -> Bitboard {
+    ATTACK_TABLES.knight[square]
+}
+
+pub fn king_attacks(square: usize) -> Bitboard {
+    ATTACK_TABLES.king[square]
+}
+
+/// Return the squares that a Pawn of `colour` on `square` attacks
+pub fn pawn_attacks(square: usize, colour: Colour) -> Bitboard {
+    ATTACK_TABLES.pawn[colour as usize][square]
+}
+
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let [file, rank, _, _] = ATTACK_TABLES.lines[square];
+    line_attacks(square, file, occupied) | line_attacks(square, rank, occupied)
+}
+
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let [_, _, diagonal, anti_diagonal] = ATTACK_TABLES.lines[square];
+    line_attacks(square, diagonal, occupied) | line_attacks(square, anti_diagonal, occupied)
+}
+
+/// Return the squares strictly between `from` and `to` if they are on a line, otherwise none
+pub fn between(from: usize, to: usize) -> Bitboard {
+    let (from_bit, to_bit): (Bitboard, Bitboard) = (1 << from, 1 << to);
+    if rook_attacks(from, 0) & to_bit != 0 {
+        rook_attacks(from, to_bit) & rook_attacks(to, from_bit)
+    } else if bishop_attacks(from, 0) & to_bit != 0 {
+        bishop_attacks(from, to_bit) & bishop_attacks(to, from_bit)
+    } else {
+        0
+    }
+}
+
+/// Return the squares attacked by `piece_type` (of `colour`, for Pawns) on `square`
+pub fn attacks(
+    piece_type: PieceType,
+    colour: Colour,
+    square: usize,
+    occupied: Bitboard,
+) -> Bitboard {
+    match piece_type {
+        Pawn => pawn_attacks(square, colour),
+        Knight => knight_attacks(square),
+        Bishop => bishop_attacks(square, occupied),
+        Rook => rook_attacks(square, occupied),
+        Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+        King => king_attacks(square),
+    }
+}
+
+/// The squares of each type of piece of each colour, kept alongside the Board
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Bitboards {
+    pieces: [[Bitboard; 6]; 2],
+    colours: [Bitboard; 2],
+}
+
+impl Bitboards {
+    pub fn from_board(board: &Board) -> Self {
+        let mut bitboards = Self::default();
+        for (&row, &column) in board_iterator() {
+            if let Some(piece) = board[row][column] {
+                bitboards.toggle(piece, Coordinate { row, column });
+            }
+        }
+        bitboards
+    }
+
+    /// Add `piece` on `at` if it is not there, otherwise remove it
+    pub fn toggle(&mut self, piece: Piece, at: Coordinate) {
+        let bit = bit(at);
+        self.pieces[piece.colour as usize][piece.piece_type as usize] ^= bit;
+        self.colours[piece.colour as usize] ^= bit;
+    }
+
+    pub fn pieces(&self, piece_type: PieceType, colour: Colour) -> Bitboard {
+        self.pieces[colour as usize][piece_type as usize]
+    }
+
+    pub fn colour(&self, colour: Colour) -> Bitboard {
+        self.colours[colour as usize]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colours[0] | self.colours[1]
+    }
+
+    /// Return the pieces of `by` that attack `square`, with the board occupied by `occupied`
+    pub fn attackers_to(&self, square: usize, by: Colour, occupied: Bitboard) -> Bitboard {
+        let queens = self.pieces(Queen, by);
+        let attackers = (pawn_attacks(square, !by) & self.pieces(Pawn, by))
+            | (knight_attacks(square) & self.pieces(Knight, by))
+            | (king_attacks(square) & self.pieces(King, by))
+            | (bishop_attacks(square, occupied) & (self.pieces(Bishop, by) | queens))
+            | (rook_attacks(square, occupied) & (self.pieces(Rook, by) | queens));
+        // pieces taken off the board do not attack
+        attackers & occupied
+    }
+
+    /// Return true iff any piece of `by` attacks `square`
+    pub fn is_attacked(&self, square: usize, by: Colour) -> bool {
+        self.attackers_to(square, by, self.occupied()) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{coordinates::ColumnIndex::*, coordinates::RowIndex::*};
+
+    fn at(row: RowIndex, column: ColumnIndex) -> usize {
+        square_index(Coordinate { row, column })
+    }
+
+    #[test]
+    fn leaper_attacks() {
+        assert_eq!(knight_attacks(at(_1, A)).count_ones(), 2);
+        assert_eq!(knight_attacks(at(_4, D)).count_ones(), 8);
+        assert_eq!(king_attacks(at(_8, H)).count_ones(), 3);
+        assert_eq!(
+            pawn_attacks(at(_2, E), White),
+            bit(Coordinate { row: _3, column: D }) | bit(Coordinate { row: _3, column: F })
+        );
+        assert_eq!(
+            pawn_attacks(at(_7, A), Black),
+            bit(Coordinate { row: _6, column: B })
+        );
+    }
+
+    #[test]
+    fn slider_attacks_stop_at_blockers() {
+        assert_eq!(rook_attacks(at(_1, A), 0).count_ones(), 14);
+        assert_eq!(bishop_attacks(at(_4, D), 0).count_ones(), 13);
+        let blocker =
+            bit(Coordinate { row: _1, column: C }) | bit(Coordinate { row: _3, column: A });
+        let expected = [(_1, B), (_1, C), (_2, A), (_3, A)]
+            .iter()
+            .fold(0, |bitboard, &(row, column)| {
+                bitboard | bit(Coordinate { row, column })
+            });
+        assert_eq!(rook_attacks(at(_1, A), blocker), expected);
+    }
+
+    #[test]
+    fn squares_round_trip() {
+        let bitboard =
+            bit(Coordinate { row: _8, column: A }) | bit(Coordinate { row: _1, column: H });
+        assert_eq!(
+            squares(bitboard).collect::<Vec<_>>(),
+            vec![
+                Coordinate { row: _8, column: A },
+                Coordinate { row: _1, column: H }
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use super::{
+    bitboard::Bitboards,
+    coordinates::ColumnIndex,
+    grid::Board,
+    moves::{CastleDirection::*, CastlingRights},
+    piece::{
+        Colour::*,
+        Piece,
+        PieceType::{self, *},
+    },
+    BoardState, PositionError,
+};
+
+/// The number of distinct Chess960 starting positions
+pub const NUM_CHESS960_POSITIONS: u16 = 960;
+
+/// The index of the standard starting position among the Chess960 starting positions
+pub const STANDARD_CHESS960_INDEX: u16 = 518;
+
+/// Return the back rank (from the A file to the H file) of the Chess960 starting position with
+/// the given index, following Scharnagl's numbering
+///
+/// Examples:
+/// ```
+/// use chess::board::{chess960::chess960_back_rank, piece::PieceType::*};
+/// assert_eq!(
+///     chess960_back_rank(518).unwrap(),
+///     [Rook, Knight, Bishop, Queen, King, Bishop, Knight, Rook]
+/// );
+/// ```
+pub fn chess960_back_rank(index: u16) -> Result<[PieceType; 8], PositionError> {
+    if index >= NUM_CHESS960_POSITIONS {
+        return Err(PositionError::InvalidChess960Index(index));
+    }
+    let mut back_rank: [Option<PieceType>; 8] = [None; 8];
+    let index = index as usize;
+
+    // Bishops on opposite colours: light squares are B, D, F and H; dark are A, C, E and G
+    back_rank[index % 4 * 2 + 1] = Some(Bishop);
+    back_rank[index / 4 % 4 * 2] = Some(Bishop);
+
+    // The rest fill the remaining empty squares, from the A file
+    fn place_on_empty(back_rank: &mut [Option<PieceType>; 8], nth_empty: usize, piece: PieceType) {
+        let file = (0..8)
+            .filter(|&file| back_rank[file].is_none())
+            .nth(nth_empty)
+            .expect("There should be enough empty squares on the back rank");
+        back_rank[file] = Some(piece);
+    }
+    let index = index / 16;
+    place_on_empty(&mut back_rank, index % 6, Queen);
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+    let (first_knight, second_knight) = KNIGHTS[index / 6];
+    // place the second Knight first, so the first Knight's count of empty squares is unchanged
+    place_on_empty(&mut back_rank, second_knight, Knight);
+    place_on_empty(&mut back_rank, first_knight, Knight);
+    for &piece in [Rook, King, Rook].iter() {
+        place_on_empty(&mut back_rank, 0, piece);
+    }
+
+    let mut result = [Pawn; 8];
+    for (file, piece) in back_rank.iter().enumerate() {
+        result[file] = piece.expect("Every square on the back rank should be filled");
+    }
+    Ok(result)
+}
+
+impl BoardState {
+    /// Return the Chess960 starting position with the given index (see `chess960_back_rank`)
+    pub fn new_chess960(index: u16) -> Result<BoardState, PositionError> {
+        let back_rank = chess960_back_rank(index)?;
+        let mut board = Board::new();
+        let mut castling_rights = CastlingRights::default();
+        for &colour in [White, Black].iter() {
+            let rank = colour.home_rank();
+            let mut king_found = false;
+            for (file, &piece_type) in back_rank.iter().enumerate() {
+                let column = ColumnIndex::from(file);
+                board[rank][column] = Some(Piece::new(piece_type, colour));
+                match piece_type {
+                    King => king_found = true,
+                    Rook if king_found => castling_rights.set(colour, UpFile, Some(column)),
+                    Rook => castling_rights.set(colour, DownFile, Some(column)),
+                    _ => {}
+                }
+            }
+        }
+        let mut board_state = BoardState {
+            bitboards: Bitboards::from_board(&board),
+            board,
+            castling_rights,
+            chess960: true,
+            ..BoardState::new()
+        };
+        board_state.hash_key = board_state.compute_hash_key();
+        Ok(board_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        chess960_back_rank, PositionError, NUM_CHESS960_POSITIONS, STANDARD_CHESS960_INDEX,
+    };
+    use crate::board::{piece::PieceType::*, BoardState};
+    use std::collections::HashSet;
+
+    #[test]
+    fn all_starting_positions_are_distinct_and_valid() {
+        let mut seen = HashSet::new();
+        for index in 0..NUM_CHESS960_POSITIONS {
+            let back_rank = chess960_back_rank(index).unwrap();
+            let files_of = |piece_type| {
+                (0..8)
+                    .filter(|&file| back_rank[file] == piece_type)
+                    .collect::<Vec<_>>()
+            };
+            let bishops = files_of(Bishop);
+            assert_eq!(bishops.len(), 2);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            let rooks = files_of(Rook);
+            let king = files_of(King);
+            assert_eq!(rooks.len(), 2);
+            assert!(rooks[0] < king[0] && king[0] < rooks[1]);
+            assert_eq!(files_of(Knight).len(), 2);
+            assert_eq!(files_of(Queen).len(), 1);
+            assert!(seen.insert(back_rank));
+        }
+        assert_eq!(
+            chess960_back_rank(NUM_CHESS960_POSITIONS),
+            Err(PositionError::InvalidChess960Index(NUM_CHESS960_POSITIONS))
+        );
+    }
+
+    #[test]
+    fn known_starting_positions() {
+        assert_eq!(
+            BoardState::new_chess960(0).unwrap().to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(
+            BoardState::new_chess960(STANDARD_CHESS960_INDEX)
+                .unwrap()
+                .to_fen(),
+            BoardState::default().to_fen()
+        );
+        assert_eq!(
+            BoardState::new_chess960(959).unwrap().to_fen(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1"
+        );
+    }
+}
@@ -1,8 +1,12 @@
 use std::fmt::{self, Display, Formatter};
 use std::iter::repeat;
+use std::str::FromStr;
 
 use enum_map::Enum;
 
+use super::piece::{Colour::Black, Piece, PieceType};
+use crate::parsing::{parse_promotion_type, CoordinateParseError, MoveParseError};
+
 #[derive(Debug, Enum, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum RowIndex {
     _8,
@@ -73,80 +77,134 @@ pub struct Coordinate {
     pub column: ColumnIndex,
 }
 
+/// Formats a Coordinate as its lower-case square (e.g. "e4")
+impl Display for Coordinate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.column as u8) as char, self.row)
+    }
+}
+
+/// Parses a square such as "e4" (the file may be in either case)
+///
+/// Examples:
+/// ```
+/// use chess::board::coordinates::{ColumnIndex::*, Coordinate, RowIndex::*};
+/// let e4: Coordinate = "e4".parse().unwrap();
+/// assert_eq!(e4, Coordinate { row: _4, column: E });
+/// assert_eq!(e4.to_string(), "e4");
+/// assert!("i4".parse::<Coordinate>().is_err());
+/// // the file is checked first
+/// assert_eq!(
+///     "x9".parse::<Coordinate>().unwrap_err().to_string(),
+///     "Invalid column x (must be a-h or A-H)"
+/// );
+/// ```
+impl FromStr for Coordinate {
+    type Err = CoordinateParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (column, row) = match input.chars().collect::<Vec<_>>()[..] {
+            [column, row] => (column, row),
+            _ => return Err(CoordinateParseError::WrongLength),
+        };
+        let column = ColumnIndex::parse(column)?;
+        Ok(Coordinate {
+            row: RowIndex::parse(row)?,
+            column,
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Move {
     pub from: Coordinate,
     pub to: Coordinate,
+    /// the type of piece to promote to, iff this move takes a Pawn to the last rank
+    pub promotion: Option<PieceType>,
 }
 
 impl Move {
+    pub fn new(from: Coordinate, to: Coordinate) -> Self {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    pub fn with_promotion(self, piece_type: PieceType) -> Self {
+        Move {
+            promotion: Some(piece_type),
+            ..self
+        }
+    }
+
     /// Return the coordinates between m.from and m.to
     ///
     /// REQ: m is in a straight line
     ///
     /// EXAMPLES:
     /// ```
-    /// use chess::{RowIndex::*, ColumnIndex::*, Coordinate, Move, squares_between};
-    /// let m = Move {
-    ///     from: Coordinate {row: _1, column: A},
-    ///     to: Coordinate {row: _4, column: D},
-    /// };
-    /// assert_eq!(squares_between(&m), vec![
+    /// use chess::board::coordinates::{RowIndex::*, ColumnIndex::*, Coordinate, Move};
+    /// let m = Move::new(
+    ///     Coordinate {row: _1, column: A},
+    ///     Coordinate {row: _4, column: D},
+    /// );
+    /// assert_eq!(m.squares_between(), vec![
     ///     Coordinate {row: _2, column: B},
     ///     Coordinate {row: _3, column: C}
     /// ]);
     /// ```
     /// ```
-    /// use chess::{RowIndex::*, ColumnIndex::*, Coordinate, Move, squares_between};
-    /// let m = Move {
-    ///     from: Coordinate {row: _7, column: G},
-    ///     to: Coordinate {row: _4, column: D},
-    /// };
-    /// assert_eq!(squares_between(&m), vec![
+    /// use chess::board::coordinates::{RowIndex::*, ColumnIndex::*, Coordinate, Move};
+    /// let m = Move::new(
+    ///     Coordinate {row: _7, column: G},
+    ///     Coordinate {row: _4, column: D},
+    /// );
+    /// assert_eq!(m.squares_between(), vec![
     ///     Coordinate {row: _6, column: F},
     ///     Coordinate {row: _5, column: E}
     /// ]);
     /// ```
     /// ```
-    /// use chess::{RowIndex::*, ColumnIndex::*, Coordinate, Move, squares_between};
-    /// let m = Move {
-    ///     from: Coordinate {row: _7, column: G},
-    ///     to: Coordinate {row: _4, column: G},
-    /// };
-    /// assert_eq!(squares_between(&m), vec![
+    /// use chess::board::coordinates::{RowIndex::*, ColumnIndex::*, Coordinate, Move};
+    /// let m = Move::new(
+    ///     Coordinate {row: _7, column: G},
+    ///     Coordinate {row: _4, column: G},
+    /// );
+    /// assert_eq!(m.squares_between(), vec![
     ///     Coordinate {row: _6, column: G},
     ///     Coordinate {row: _5, column: G}
     /// ]);
     /// ```
     /// ```
-    /// use chess::{RowIndex::*, ColumnIndex::*, Coordinate, Move, squares_between};
-    /// let m = Move {
-    ///     from: Coordinate {row: _7, column: G},
-    ///     to: Coordinate {row: _7, column: D},
-    /// };
-    /// assert_eq!(squares_between(&m), vec![
+    /// use chess::board::coordinates::{RowIndex::*, ColumnIndex::*, Coordinate, Move};
+    /// let m = Move::new(
+    ///     Coordinate {row: _7, column: G},
+    ///     Coordinate {row: _7, column: D},
+    /// );
+    /// assert_eq!(m.squares_between(), vec![
     ///     Coordinate {row: _7, column: F},
     ///     Coordinate {row: _7, column: E}
     /// ]);
     /// ```
     /// ```
-    /// use chess::{RowIndex::*, ColumnIndex::*, Coordinate, Move, squares_between};
-    /// let m = Move {
-    ///     from: Coordinate {row: _7, column: D},
-    ///     to: Coordinate {row: _7, column: G},
-    /// };
-    /// assert_eq!(squares_between(&m), vec![
+    /// use chess::board::coordinates::{RowIndex::*, ColumnIndex::*, Coordinate, Move};
+    /// let m = Move::new(
+    ///     Coordinate {row: _7, column: D},
+    ///     Coordinate {row: _7, column: G},
+    /// );
+    /// assert_eq!(m.squares_between(), vec![
     ///     Coordinate {row: _7, column: E},
     ///     Coordinate {row: _7, column: F}
     /// ]);
     /// ```
     /// ```
-    /// use chess::{RowIndex::*, ColumnIndex::*, Coordinate, Move, squares_between};
-    /// let m = Move {
-    ///     from: Coordinate {row: _1, column: A},
-    ///     to: Coordinate {row: _2, column: B},
-    /// };
-    /// assert_eq!(squares_between(&m), vec![]);
+    /// use chess::board::coordinates::{RowIndex::*, ColumnIndex::*, Coordinate, Move};
+    /// let m = Move::new(
+    ///     Coordinate {row: _1, column: A},
+    ///     Coordinate {row: _2, column: B},
+    /// );
+    /// assert_eq!(m.squares_between(), vec![]);
     /// ```
     pub fn squares_between(&self) -> Vec<Coordinate> {
         // Columns
@@ -175,8 +233,8 @@ impl Move {
             column: *column,
         };
         // Equate sizes, reverse if needed, zip and map to Coordinates
-        if (rows.len() == 0) ^ (columns.len() == 0) {
-            if rows.len() == 0 {
+        if rows.is_empty() ^ columns.is_empty() {
+            if rows.is_empty() {
                 let e = &RowIndex::get_rows()[from_row];
                 if from_column > to_column {
                     repeat(e)
@@ -222,3 +280,51 @@ impl Move {
         }
     }
 }
+
+/// Formats a Move in UCI long algebraic notation: the two squares, then the lower-case letter of
+/// any promotion (e.g. "e2e4" or "e7e8q")
+///
+/// Note: castling is written as the King's move (e.g. "e1g1"), or as the King taking its own
+/// Rook in Chess960, as with the UCI_Chess960 option.
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(piece_type) => write!(f, "{}", Piece::new(piece_type, Black).to_char()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a Move in UCI long algebraic notation
+///
+/// Examples:
+/// ```
+/// use chess::board::{coordinates::{ColumnIndex::*, Coordinate, Move, RowIndex::*}, piece::PieceType::*};
+/// let m: Move = "e7e8q".parse().unwrap();
+/// assert_eq!(
+///     m,
+///     Move::new(Coordinate { row: _7, column: E }, Coordinate { row: _8, column: E })
+///         .with_promotion(Queen)
+/// );
+/// assert_eq!(m.to_string(), "e7e8q");
+/// assert_eq!("g1f3".parse::<Move>().unwrap().to_string(), "g1f3");
+/// assert!("e7e8k".parse::<Move>().is_err());
+/// ```
+impl FromStr for Move {
+    type Err = MoveParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = input.chars().collect();
+        let (from, to, promotion) = match chars[..] {
+            [_, _, _, _] => (&chars[0..2], &chars[2..4], None),
+            [_, _, _, _, promotion] => (&chars[0..2], &chars[2..4], Some(promotion)),
+            _ => return Err(MoveParseError::WrongLength),
+        };
+        let parse = |square: &[char]| square.iter().collect::<String>().parse::<Coordinate>();
+        let m = Move::new(parse(from)?, parse(to)?);
+        match promotion {
+            Some(promotion) => Ok(m.with_promotion(parse_promotion_type(&promotion.to_string())?)),
+            None => Ok(m),
+        }
+    }
+}
@@ -1,73 +1,352 @@
 use std::convert::TryInto;
-use std::str::FromStr;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
 
 use enum_map::EnumMap;
 
-use crate::parsing::parse_coordinate;
+use crate::parsing::CoordinateParseError;
 
 use super::{
-    coordinates::{ColumnIndex, Coordinate, RowIndex},
+    bitboard::Bitboards,
+    coordinates::{ColumnIndex, Coordinate, Move, RowIndex},
     grid::Board,
-    moves::MoveRecords,
-    piece::{Colour, Movable},
-    BoardState, CastlingAvailability,
+    moves::{
+        CastleDirection::{self, *},
+        CastlingRights, MoveRecords,
+    },
+    piece::{
+        Colour, Movable,
+        PieceType::{King, Rook},
+    },
+    san::SanError,
+    BoardState,
     Colour::*,
     ColumnIndex::*,
     Piece,
 };
 
-fn parse_castling_availability(fen_castling_field: &str) -> Result<CastlingAvailability, String> {
-    // TODO: prevent "buffer overflow"
-    let fen_castling_field_chars: Vec<char> = fen_castling_field.chars().collect();
-    let is_shredder = match fen_castling_field_chars[..] {
-        [] => {
-            return Err(String::from(
-                "FEN PARSE ERROR: castling availability must not be blank",
-            ))
+/// The reason that a FEN record could not be parsed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FenError {
+    WrongNumberOfFields(usize),
+    InvalidActivePlayer(String),
+    WrongNumberOfRows(usize),
+    /// a row (counted from rank 8) that does not describe exactly 8 squares
+    WrongRowLength {
+        row: usize,
+        length: usize,
+    },
+    InvalidPiece(char),
+    EmptyCastlingField,
+    NoKingToCastle(Colour),
+    InvalidCastlingCharacter(char),
+    RepeatedCastlingCharacter(char),
+    InvalidEnPassantSquare(CoordinateParseError),
+    InvalidMoveCounter {
+        name: &'static str,
+        value: String,
+    },
+    ZeroFullmoveNumber,
+    /// an EPD record without the four fields of its position
+    WrongNumberOfEpdFields(usize),
+    /// an EPD operation with a string operand that is not closed
+    UnterminatedOperand(String),
+    /// an EPD operation whose operand should be a non-negative integer
+    InvalidOperand {
+        opcode: String,
+        operand: String,
+    },
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "FEN PARSE ERROR: ")?;
+        match self {
+            FenError::WrongNumberOfFields(num_fields) => {
+                write!(f, "wrong number of fields in record ({}/6)", num_fields)
+            }
+            FenError::InvalidActivePlayer(active_player) => {
+                write!(f, "next player must be 'b' or 'w' (not {})", active_player)
+            }
+            FenError::WrongNumberOfRows(num_rows) => {
+                write!(f, "wrong number of rows ({}/8)", num_rows)
+            }
+            FenError::WrongRowLength { row, length } => {
+                write!(f, "wrong number of pieces ({}/8) in row {}", length, row)
+            }
+            FenError::InvalidPiece(c) => write!(f, "invalid character for square {}", c),
+            FenError::EmptyCastlingField => {
+                write!(f, "castling availability must not be blank")
+            }
+            FenError::NoKingToCastle(colour) => write!(
+                f,
+                "{:?} cannot castle without a King on its home rank",
+                colour
+            ),
+            FenError::InvalidCastlingCharacter(c) => {
+                write!(f, "invalid character in availability field {}", c)
+            }
+            FenError::RepeatedCastlingCharacter(c) => {
+                write!(f, "repeated character in availability field {}", c)
+            }
+            FenError::InvalidEnPassantSquare(error) => {
+                write!(f, "invalid en-passant target square ({})", error)
+            }
+            FenError::InvalidMoveCounter { name, value } => {
+                write!(f, "{} must be a non-negative integer (not {})", name, value)
+            }
+            FenError::ZeroFullmoveNumber => write!(f, "fullmove number must start at 1"),
+            FenError::WrongNumberOfEpdFields(num_fields) => {
+                write!(f, "wrong number of fields in EPD record ({}/4)", num_fields)
+            }
+            FenError::UnterminatedOperand(opcode) => {
+                write!(f, "unterminated string operand of {}", opcode)
+            }
+            FenError::InvalidOperand { opcode, operand } => write!(
+                f,
+                "operand of {} must be a non-negative integer (not {})",
+                opcode, operand
+            ),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// Formats a BoardState as a FEN record
+pub struct Fen<'a>(pub &'a BoardState);
+
+impl BoardState {
+    /// Return the FEN record of this position
+    ///
+    /// Castling availability is written as in X-FEN, so that Chess960 positions can be recorded.
+    ///
+    /// Examples:
+    /// ```
+    /// use chess::board::BoardState;
+    /// assert_eq!(
+    ///     BoardState::default().to_fen(),
+    ///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    /// );
+    /// ```
+    pub fn to_fen(&self) -> String {
+        Fen(self).to_string()
+    }
+}
+
+/// Formats a BoardState as a Shredder-FEN record, which gives every castling right by the file
+/// of its Rook
+pub struct ShredderFen<'a>(pub &'a BoardState);
+
+impl BoardState {
+    /// Return the Shredder-FEN record of this position
+    ///
+    /// Examples:
+    /// ```
+    /// use chess::board::BoardState;
+    /// assert_eq!(
+    ///     BoardState::default().to_shredder_fen(),
+    ///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+    /// );
+    /// ```
+    pub fn to_shredder_fen(&self) -> String {
+        ShredderFen(self).to_string()
+    }
+}
+
+impl Display for Fen<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_fen(f, self.0, false)
+    }
+}
+
+impl Display for ShredderFen<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_fen(f, self.0, true)
+    }
+}
+
+/// Write the FEN record of `board_state`, giving castling rights as in X-FEN (K/Q/k/q for the
+/// outermost Rook on each side, and the file of the Rook otherwise), or always by file if
+/// `shredder` is set
+fn write_fen(f: &mut Formatter<'_>, board_state: &BoardState, shredder: bool) -> fmt::Result {
+    for (row_index, &row) in RowIndex::get_rows().iter().enumerate() {
+        if row_index != 0 {
+            write!(f, "/")?;
+        }
+        let mut num_empty = 0;
+        for &column in ColumnIndex::get_columns() {
+            match board_state.board[row][column] {
+                Some(piece) => {
+                    if num_empty != 0 {
+                        write!(f, "{}", num_empty)?;
+                        num_empty = 0;
+                    }
+                    write!(f, "{}", piece.to_char())?;
+                }
+                None => num_empty += 1,
+            }
         }
-        ['-'] => return Ok(CastlingAvailability(Default::default())),
-        ['k' | 'K' | 'q' | 'Q', ..] => false,
-        _ => true,
+        if num_empty != 0 {
+            write!(f, "{}", num_empty)?;
+        }
+    }
+
+    let active_player = match board_state.current_player {
+        White => 'w',
+        Black => 'b',
+    };
+    write!(f, " {} ", active_player)?;
+
+    let castling_rights = board_state.castling_rights;
+    let mut castling_field = String::new();
+    for &(colour, direction, x_fen_c) in [
+        (White, UpFile, 'K'),
+        (White, DownFile, 'Q'),
+        (Black, UpFile, 'k'),
+        (Black, DownFile, 'q'),
+    ]
+    .iter()
+    {
+        match castling_rights.get(colour, direction) {
+            Some(file)
+                if !shredder
+                    && find_outermost_rook(&board_state.board, colour, direction)
+                        .is_none_or(|outermost| outermost == file) =>
+            {
+                castling_field.push(x_fen_c)
+            }
+            Some(file) => {
+                let c = file.to_string().chars().next().unwrap();
+                castling_field.push(match colour {
+                    White => c,
+                    Black => c.to_ascii_lowercase(),
+                });
+            }
+            None => {}
+        }
+    }
+    if castling_field.is_empty() {
+        castling_field.push('-');
+    }
+    write!(f, "{} ", castling_field)?;
+
+    match board_state.en_passant_availability {
+        Some(Coordinate { row, column }) => {
+            write!(f, "{}{}", column.to_string().to_ascii_lowercase(), row)?
+        }
+        None => write!(f, "-")?,
+    }
+
+    write!(
+        f,
+        " {} {}",
+        board_state.get_halfmove_clock(),
+        board_state.fullmove_number
+    )
+}
+
+/// Return the file of the King of `colour` on its home rank, if it is there
+fn find_home_king(board: &Board, colour: Colour) -> Option<ColumnIndex> {
+    ColumnIndex::get_columns().iter().copied().find(|&column| {
+        matches!(
+            board[colour.home_rank()][column],
+            Some(Piece { piece_type: King, colour: c, .. }) if c == colour
+        )
+    })
+}
+
+/// Return the file of the Rook of `colour` on its home rank that is furthest from its King in
+/// `direction`, if there is one
+fn find_outermost_rook(
+    board: &Board,
+    colour: Colour,
+    direction: CastleDirection,
+) -> Option<ColumnIndex> {
+    let king_file = find_home_king(board, colour)?;
+    let columns = ColumnIndex::get_columns().iter().copied();
+    let is_own_rook = |&column: &ColumnIndex| {
+        matches!(
+            board[colour.home_rank()][column],
+            Some(Piece { piece_type: Rook, colour: c, .. }) if c == colour
+        )
     };
+    match direction {
+        UpFile => columns
+            .rev()
+            .take_while(|&c| c > king_file)
+            .find(is_own_rook),
+        DownFile => columns.take_while(|&c| c < king_file).find(is_own_rook),
+    }
+}
 
-    let mut map: EnumMap<Colour, EnumMap<ColumnIndex, bool>> = Default::default();
-    for c in fen_castling_field_chars {
-        let (colour, file) = match c {
-            'k' if !is_shredder => (Black, A),
-            'K' if !is_shredder => (White, A),
-            'q' if !is_shredder => (Black, H),
-            'Q' if !is_shredder => (White, H),
-            'a'..='h' if is_shredder => (Black, ColumnIndex::parse(c)?),
-            'A'..='H' if is_shredder => (White, ColumnIndex::parse(c)?),
-            _ => {
-                return Err(format!(
-                    "FEN PARSE ERROR: invalid character in availability field {}",
-                    c
-                ))
+/// Parse the castling availability field, in any mix of standard FEN, X-FEN and Shredder-FEN
+fn parse_castling_rights(
+    fen_castling_field: &str,
+    board: &Board,
+) -> Result<CastlingRights, FenError> {
+    match fen_castling_field {
+        "" => return Err(FenError::EmptyCastlingField),
+        "-" => return Ok(CastlingRights::default()),
+        _ => {}
+    }
+
+    let mut castling_rights = CastlingRights::default();
+    for c in fen_castling_field.chars() {
+        let colour = if c.is_ascii_uppercase() { White } else { Black };
+        let (direction, file) = match c {
+            // the outermost Rook on that side, or the corner if there is no such Rook
+            'K' | 'k' => (
+                UpFile,
+                find_outermost_rook(board, colour, UpFile).unwrap_or(H),
+            ),
+            'Q' | 'q' => (
+                DownFile,
+                find_outermost_rook(board, colour, DownFile).unwrap_or(A),
+            ),
+            'a'..='h' | 'A'..='H' => {
+                let file =
+                    ColumnIndex::parse(c).map_err(|_| FenError::InvalidCastlingCharacter(c))?;
+                let king_file =
+                    find_home_king(board, colour).ok_or(FenError::NoKingToCastle(colour))?;
+                let direction = if file > king_file { UpFile } else { DownFile };
+                (direction, file)
             }
+            _ => return Err(FenError::InvalidCastlingCharacter(c)),
         };
-        if map[colour][file] {
-            return Err(format!(
-                "FEN PARSE ERROR: repeated character in availability field {}",
-                c
-            ));
+        if castling_rights.get(colour, direction).is_some() {
+            return Err(FenError::RepeatedCastlingCharacter(c));
         }
-        map[colour][file] = true; // TODO: check repeats
+        castling_rights.set(colour, direction, Some(file));
     }
 
-    Ok(CastlingAvailability(map))
+    Ok(castling_rights)
 }
 
-fn parse_board(
-    fen_pieces_field: &str,
-    castling_availability: &CastlingAvailability,
-) -> Result<Board, String> {
-    // TODO: prevent "buffer overflow"
+/// Return whether a position with these castling rights can only be a Chess960 position, since
+/// a King or castling Rook is not where it starts in standard chess
+fn is_chess960_position(board: &Board, castling_rights: &CastlingRights) -> bool {
+    [White, Black].iter().any(|&colour| {
+        let standard_rights = [(UpFile, H), (DownFile, A)]
+            .iter()
+            .all(|&(direction, corner)| {
+                castling_rights
+                    .get(colour, direction)
+                    .is_none_or(|file| file == corner)
+            });
+        !standard_rights
+            || (castling_rights.can_castle(colour) && find_home_king(board, colour) != Some(E))
+    })
+}
+
+fn parse_board(fen_pieces_field: &str) -> Result<Board, FenError> {
     let rows: Vec<_> = fen_pieces_field.split('/').collect();
     let num_rows = rows.len();
     let rows: [&str; 8] = rows
         .try_into()
-        .map_err(|_| format!("FEN PARSE ERROR: wrong number of rows ({}/8)", num_rows))?;
+        .map_err(|_| FenError::WrongNumberOfRows(num_rows))?;
 
     let row_maps = rows
         .iter() // TODO: use into_iter
@@ -80,49 +359,71 @@ fn parse_board(
                     num_empty @ '1'..='8' => {
                         index += num_empty as usize - '0' as usize;
                     }
+                    _ if index >= 8 => {
+                        return Err(FenError::WrongRowLength {
+                            row: row_index,
+                            length: row
+                                .chars()
+                                .map(|c| c.to_digit(10).unwrap_or(1) as usize)
+                                .sum(),
+                        })
+                    }
                     _ => {
                         let mut new_piece = Piece::from_char(c)?;
-
-                        let column_index = ColumnIndex::from(index);
-                        let row_index = RowIndex::from(row_index);
-                        new_piece.guess_and_set_is_moved(
-                            row_index,
-                            column_index,
-                            castling_availability,
-                        );
+                        new_piece.guess_and_set_is_moved(RowIndex::from(row_index));
 
                         pieces[index] = new_piece;
                         index += 1;
                     }
                 }
             }
-            if index == 7 {
+            if index == 8 {
                 Ok(EnumMap::from_array(pieces))
             } else {
-                Err(format!(
-                    "FEN PARSE ERROR: wrong number of pieces ({}/8) in row {}, ",
-                    index, row_index
-                ))
+                Err(FenError::WrongRowLength {
+                    row: row_index,
+                    length: index,
+                })
             }
         })
-        .collect::<Result<Vec<_>, String>>()?;
+        .collect::<Result<Vec<_>, FenError>>()?;
 
     Ok(Board(EnumMap::from_array(row_maps.try_into().unwrap())))
 }
 
 fn parse_en_passant_availability(
     fen_en_passant_availability_field: &str,
-) -> Result<Option<Coordinate>, String> {
+) -> Result<Option<Coordinate>, FenError> {
     if fen_en_passant_availability_field.len() == 1 {
         if let Some('-') = fen_en_passant_availability_field.chars().next() {
             return Ok(None);
         }
     }
-    parse_coordinate(fen_en_passant_availability_field).and_then(|coordinate| Ok(Some(coordinate)))
+    fen_en_passant_availability_field
+        .parse()
+        .map(Some)
+        .map_err(FenError::InvalidEnPassantSquare)
+}
+
+/// Parse a non-negative move counter, treating "-" as unspecified
+fn parse_move_counter(
+    fen_counter_field: &str,
+    default: u32,
+    name: &'static str,
+) -> Result<u32, FenError> {
+    match fen_counter_field {
+        "-" => Ok(default),
+        _ => fen_counter_field
+            .parse()
+            .map_err(|_| FenError::InvalidMoveCounter {
+                name,
+                value: String::from(fen_counter_field),
+            }),
+    }
 }
 
 impl FromStr for BoardState {
-    type Err = String;
+    type Err = FenError;
     fn from_str(fen_string: &str) -> Result<Self, Self::Err> {
         let fields: Vec<_> = fen_string.split(" ").collect();
         let num_fields = fields.len();
@@ -165,42 +466,224 @@ impl FromStr for BoardState {
                         //  - If a pawn has just made a two-square move, this is the position "behind" the pawn. 
                         //  - This is recorded regardless of whether there is a pawn in position to make an en passant capture.
 
-            _halfmove_clock, // Halfmove clock: The number of halfmoves since the last capture or pawn advance, used for the fifty-move rule.
+            halfmove_clock, // Halfmove clock: The number of halfmoves since the last capture or pawn advance, used for the fifty-move rule.
 
-            _full_moves_number, // Fullmove number: The number of the full move. It starts at 1, and is incremented after Black's move.
+            full_moves_number, // Fullmove number: The number of the full move. It starts at 1, and is incremented after Black's move.
 
-        ]: [&str; 6] = fields.try_into().map_err(|_| format!("FEN PARSE ERROR: wrong number of fields in record ({}/6)", num_fields))?;
+        ]: [&str; 6] = fields.try_into().map_err(|_| FenError::WrongNumberOfFields(num_fields))?;
 
         let current_player = match active_player {
             "B" | "b" => Black,
             "W" | "w" => White,
-            _ => {
-                return Err(format!(
-                    "FEN PARSE ERROR: next player must be 'b' or 'w' (not {})",
-                    active_player
-                ))
-            }
+            _ => return Err(FenError::InvalidActivePlayer(String::from(active_player))),
         };
 
-        let castling_availability = parse_castling_availability(castling_availability)?;
+        let board = parse_board(pieces)?;
+        let castling_rights = parse_castling_rights(castling_availability, &board)?;
+        let chess960 = is_chess960_position(&board, &castling_rights);
+        let bitboards = Bitboards::from_board(&board);
         let en_passant_availability = parse_en_passant_availability(en_passant)?;
 
-        let board = parse_board(pieces, &castling_availability)?;
+        let halfmove_clock = parse_move_counter(halfmove_clock, 0, "halfmove clock")?;
+        let fullmove_number = parse_move_counter(full_moves_number, 1, "fullmove number")?;
+        if fullmove_number == 0 {
+            return Err(FenError::ZeroFullmoveNumber);
+        }
 
-        let moves = MoveRecords::new(en_passant_availability);
+        let moves = MoveRecords::new(en_passant_availability, halfmove_clock);
 
-        Ok(Self {
+        let mut board_state = Self {
             current_player,
             board,
             moves,
             en_passant_availability,
-        })
+            fullmove_number,
+            castling_rights,
+            chess960,
+            hash_key: 0,
+            bitboards,
+        };
+        board_state.hash_key = board_state.compute_hash_key();
+        Ok(board_state)
+    }
+}
+
+/// The EPD opcodes whose operands are non-negative integers
+const NUMERIC_OPCODES: [&str; 6] = ["acd", "acn", "acs", "dm", "fmvn", "hmvc"];
+
+/// An EPD (Extended Position Description) record: the first four fields of a FEN record,
+/// followed by operations such as `bm Nf3; id "test.1";`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Epd {
+    /// The position, with the move counters given by the hmvc and fmvn operations (if any)
+    pub board_state: BoardState,
+    /// Each opcode with its operands, in the order given (without the quotes of strings)
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    /// Return the operands of the first operation with `opcode`, if any
+    pub fn get_operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    fn get_string(&self, opcode: &str) -> Option<&str> {
+        self.get_operands(opcode)
+            .and_then(|operands| operands.first())
+            .map(String::as_str)
+    }
+
+    fn get_number(&self, opcode: &str) -> Option<u32> {
+        self.get_string(opcode)
+            .and_then(|operand| operand.parse().ok())
+    }
+
+    /// Return the moves given as SAN operands of `opcode` (none if there is no such operation)
+    fn get_moves(&self, opcode: &str) -> Result<Vec<Move>, SanError> {
+        let mut board_state = self.board_state.clone();
+        self.get_operands(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| board_state.from_san(san))
+            .collect()
+    }
+
+    /// Return the best moves (bm) of the position
+    pub fn best_moves(&self) -> Result<Vec<Move>, SanError> {
+        self.get_moves("bm")
+    }
+
+    /// Return the moves to avoid (am) in the position
+    pub fn avoid_moves(&self) -> Result<Vec<Move>, SanError> {
+        self.get_moves("am")
+    }
+
+    /// Return the identifier (id) of the position
+    pub fn id(&self) -> Option<&str> {
+        self.get_string("id")
+    }
+
+    /// Return the primary comment (c0) on the position
+    pub fn comment(&self) -> Option<&str> {
+        self.get_string("c0")
+    }
+
+    /// Return the number of moves to a direct mate (dm)
+    pub fn direct_mate(&self) -> Option<u32> {
+        self.get_number("dm")
+    }
+
+    /// Return the analysis count depth (acd): the number of plies searched
+    pub fn analysis_depth(&self) -> Option<u32> {
+        self.get_number("acd")
+    }
+}
+
+/// Return the characters up to (but not including) the first one matching `end`
+fn take_until(chars: &mut Peekable<Chars>, end: impl Fn(char) -> bool) -> String {
+    let mut taken = String::new();
+    while let Some(&c) = chars.peek() {
+        if end(c) {
+            break;
+        }
+        taken.push(c);
+        chars.next();
+    }
+    taken
+}
+
+/// Parse the operations of an EPD record (everything after its fourth field)
+fn parse_operations(epd_operations: &str) -> Result<Vec<(String, Vec<String>)>, FenError> {
+    let mut operations = vec![];
+    let mut chars = epd_operations.chars().peekable();
+    loop {
+        take_until(&mut chars, |c| !c.is_whitespace());
+        if chars.peek().is_none() {
+            break;
+        }
+        let opcode = take_until(&mut chars, |c| c.is_whitespace() || c == ';');
+        let mut operands = vec![];
+        // the last operation may be missing its ';'
+        loop {
+            take_until(&mut chars, |c| !c.is_whitespace());
+            match chars.next() {
+                None | Some(';') => break,
+                Some('"') => {
+                    operands.push(take_until(&mut chars, |c| c == '"'));
+                    if chars.next().is_none() {
+                        return Err(FenError::UnterminatedOperand(opcode));
+                    }
+                }
+                Some(c) => {
+                    let operand = take_until(&mut chars, |c| c.is_whitespace() || c == ';');
+                    operands.push(format!("{}{}", c, operand));
+                }
+            }
+        }
+        if NUMERIC_OPCODES.contains(&opcode.as_str()) {
+            if let Some(operand) = operands
+                .iter()
+                .find(|operand| operand.parse::<u32>().is_err())
+            {
+                return Err(FenError::InvalidOperand {
+                    opcode,
+                    operand: operand.clone(),
+                });
+            }
+        }
+        operations.push((opcode, operands));
+    }
+    Ok(operations)
+}
+
+/// Parses an EPD record
+///
+/// Examples:
+/// ```
+/// use chess::board::fen::Epd;
+/// let epd: Epd = r#"1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - bm Qd1+; id "BK.01";"#
+///     .parse()
+///     .unwrap();
+/// assert_eq!(epd.id(), Some("BK.01"));
+/// assert_eq!(epd.best_moves().unwrap().len(), 1);
+/// ```
+impl FromStr for Epd {
+    type Err = FenError;
+    fn from_str(epd_string: &str) -> Result<Self, Self::Err> {
+        let mut rest = epd_string.trim_start();
+        let mut fields = vec![];
+        while fields.len() < 4 && !rest.is_empty() {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+        if fields.len() < 4 {
+            return Err(FenError::WrongNumberOfEpdFields(fields.len()));
+        }
+        let operations = parse_operations(rest)?;
+        let epd = Epd {
+            board_state: BoardState::default(),
+            operations,
+        };
+        let halfmove_clock = epd.get_string("hmvc").unwrap_or("0");
+        let fullmove_number = epd.get_string("fmvn").unwrap_or("1");
+        let board_state = BoardState::from_str(&format!(
+            "{} {} {}",
+            fields.join(" "),
+            halfmove_clock,
+            fullmove_number
+        ))?;
+        Ok(Epd { board_state, ..epd })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::BoardState;
+    use super::{BoardState, Epd, FenError};
+    use crate::{board::piece::Colour::White, parsing::CoordinateParseError};
     use std::str::FromStr;
 
     #[test]
@@ -210,8 +693,191 @@ mod tests {
             board: Default::default(),
             moves: Default::default(),
             en_passant_availability: None,
+            fullmove_number: 1,
+            castling_rights: Default::default(),
+            chess960: false,
+            hash_key: 0,
+            bitboards: Default::default(),
         };
         let actual = BoardState::from_str("8/8/8/8/8/8/8/8 w - - - -").unwrap();
         assert_eq!(expect, actual);
     }
+
+    const FEN_CORPUS: [&str; 15] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        "r3k3/8/8/8/8/8/8/4K2R w Kq - 12 40",
+        "8/8/4k3/8/8/4K3/8/8 b - - 99 120",
+        "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1",
+        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1",
+        "r3k2r/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1",
+        "rk2r3/8/8/8/8/8/8/RRK5 b Bkq - 0 1",
+    ];
+
+    #[test]
+    fn fen_round_trip() {
+        for &fen in FEN_CORPUS.iter() {
+            let board_state = BoardState::from_str(fen).unwrap();
+            assert_eq!(board_state.to_fen(), fen);
+            let reparsed = BoardState::from_str(&board_state.to_fen()).unwrap();
+            assert_eq!(reparsed, board_state);
+        }
+    }
+
+    #[test]
+    fn fen_round_trip_after_moves() {
+        for &fen in FEN_CORPUS.iter() {
+            let mut board_state = BoardState::from_str(fen).unwrap();
+            let player = board_state.get_next_player();
+            for m in board_state.get_legal_moves(player) {
+                board_state.try_move(m).unwrap();
+                let reparsed = BoardState::from_str(&board_state.to_fen()).unwrap();
+                assert_eq!(reparsed.to_fen(), board_state.to_fen());
+                assert_eq!(reparsed.hash_key(), board_state.hash_key());
+                assert_eq!(reparsed.castling_rights, board_state.castling_rights);
+                board_state.undo_move();
+            }
+        }
+    }
+
+    #[test]
+    fn chess960_castling_fields() {
+        let board_state = BoardState::from_str("rk2r3/8/8/8/8/8/8/RRK5 b Bkq - 0 1").unwrap();
+        assert!(board_state.chess960);
+        assert_eq!(
+            board_state.to_shredder_fen(),
+            "rk2r3/8/8/8/8/8/8/RRK5 b Bea - 0 1"
+        );
+        assert_eq!(
+            BoardState::from_str(&board_state.to_shredder_fen()).unwrap(),
+            board_state
+        );
+        // Shredder-FEN and X-FEN letters may be mixed
+        assert_eq!(
+            BoardState::from_str("rk2r3/8/8/8/8/8/8/RRK5 b Bea - 0 1").unwrap(),
+            BoardState::from_str("rk2r3/8/8/8/8/8/8/RRK5 b Bkq - 0 1").unwrap()
+        );
+        assert!(!BoardState::default().chess960);
+        assert!(BoardState::from_str("rk2r3/8/8/8/8/8/8/RRK5 b BQkq - 0 1").is_err());
+    }
+
+    #[test]
+    fn move_counters() {
+        let board_state = BoardState::from_str("8/8/4k3/8/8/4K3/8/8 b - - 37 52").unwrap();
+        assert_eq!(board_state.get_halfmove_clock(), 37);
+        assert_eq!(board_state.get_fullmove_number(), 52);
+    }
+
+    #[test]
+    fn invalid_move_counters() {
+        assert_eq!(
+            BoardState::from_str("8/8/4k3/8/8/4K3/8/8 b - - x 52"),
+            Err(FenError::InvalidMoveCounter {
+                name: "halfmove clock",
+                value: String::from("x")
+            })
+        );
+        assert_eq!(
+            BoardState::from_str("8/8/4k3/8/8/4K3/8/8 b - - 3 0"),
+            Err(FenError::ZeroFullmoveNumber)
+        );
+    }
+
+    #[test]
+    fn epd_operations() {
+        let epd: Epd = r#"r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - bm Nxc6 Qd2;am Bb5;id "WAC; 1" ; c0 "two  spaces"; dm 3; acd 12; hmvc 4; fmvn 7"#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            epd.board_state.to_fen(),
+            "r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - 4 7"
+        );
+        assert_eq!(epd.best_moves().unwrap().len(), 2);
+        assert_eq!(epd.avoid_moves().unwrap().len(), 1);
+        assert_eq!(epd.id(), Some("WAC; 1"));
+        assert_eq!(epd.comment(), Some("two  spaces"));
+        assert_eq!(epd.direct_mate(), Some(3));
+        assert_eq!(epd.analysis_depth(), Some(12));
+        assert_eq!(epd.get_operands("bm").unwrap(), ["Nxc6", "Qd2"]);
+
+        let epd: Epd = "4k3/8/8/8/8/8/8/4K3 b - -".parse().unwrap();
+        assert!(epd.operations.is_empty());
+        assert_eq!(epd.best_moves(), Ok(vec![]));
+        assert_eq!(epd.id(), None);
+        let epd: Epd = "4k3/8/8/8/8/8/8/4K3 b - - bm Kd1".parse().unwrap();
+        assert!(epd.best_moves().is_err());
+    }
+
+    #[test]
+    fn epd_errors() {
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 b -".parse::<Epd>(),
+            Err(FenError::WrongNumberOfEpdFields(3))
+        );
+        assert_eq!(
+            r#"4k3/8/8/8/8/8/8/4K3 b - - id "open"#.parse::<Epd>(),
+            Err(FenError::UnterminatedOperand(String::from("id")))
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 b - - acd x;".parse::<Epd>(),
+            Err(FenError::InvalidOperand {
+                opcode: String::from("acd"),
+                operand: String::from("x")
+            })
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 x - - id \"bad side\";".parse::<Epd>(),
+            Err(FenError::InvalidActivePlayer(String::from("x")))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8/8 w - -"),
+            Err(FenError::WrongNumberOfFields(4))
+        );
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8/8 x - - 0 1"),
+            Err(FenError::InvalidActivePlayer(String::from("x")))
+        );
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8 w - - 0 1"),
+            Err(FenError::WrongNumberOfRows(7))
+        );
+        // pieces past the end of a row are reported rather than written off the board
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8/4K3PP w - - 0 1"),
+            Err(FenError::WrongRowLength { row: 7, length: 10 })
+        );
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8/4K2X w - - 0 1"),
+            Err(FenError::InvalidPiece('X'))
+        );
+        assert_eq!(
+            BoardState::from_str("4k3/8/8/8/8/8/8/4K3 w X - 0 1"),
+            Err(FenError::InvalidCastlingCharacter('X'))
+        );
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8/8 w B - 0 1"),
+            Err(FenError::NoKingToCastle(White))
+        );
+        assert_eq!(
+            BoardState::from_str("8/8/8/8/8/8/8/8 w - e9 0 1"),
+            Err(FenError::InvalidEnPassantSquare(
+                CoordinateParseError::InvalidRow('9')
+            ))
+        );
+        assert_eq!(
+            FenError::WrongNumberOfRows(7).to_string(),
+            "FEN PARSE ERROR: wrong number of rows (7/8)"
+        );
+    }
 }
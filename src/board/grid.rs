@@ -4,6 +4,7 @@ use std::ops::{Deref, DerefMut};
 use std::slice;
 
 use crate::board::coordinates::*;
+use crate::board::moves::MoveError;
 use crate::board::piece::*;
 use Colour::*;
 use ColumnIndex::*;
@@ -14,10 +15,11 @@ pub type Square = Option<Piece>;
 pub type Row = EnumMap<ColumnIndex, Square>;
 pub type BoardMap = EnumMap<RowIndex, Row>;
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Clone)]
 pub struct Board(pub BoardMap);
 
 impl Board {
+    #[allow(clippy::just_underscores_and_digits)]
     pub fn new() -> Self {
         Board(enum_map! {
             _8 => enum_map!{
@@ -125,17 +127,11 @@ pub fn board_iterator() -> BoardIterator {
 }
 
 /// REQ: m is in a straight line
-pub fn has_no_pieces_between<'a>(
-    board: &'a Board,
-    m: &Move,
-) -> Result<(), (&'a Piece, Coordinate)> {
-    for coordinate in m.squares_between() {
-        match &board[coordinate.row][coordinate.column] {
-            None => {}
-            Some(piece) => {
-                return Err((piece, coordinate));
-            }
-        };
+pub fn has_no_pieces_between(board: &Board, m: &Move) -> Result<(), MoveError> {
+    for at in m.squares_between() {
+        if let Some(piece) = board[at.row][at.column] {
+            return Err(MoveError::Blocked { piece, at });
+        }
     }
     Ok(())
 }
@@ -0,0 +1,335 @@
+use super::{
+    bitboard::{self, between, bit, square_index, squares, Bitboard},
+    coordinates::{ColumnIndex::*, Coordinate, Move, RowIndex},
+    moves::{CastleDirection::*, MoveRecord},
+    piece::{
+        Colour::{self, *},
+        Piece,
+        PieceType::{self, *},
+    },
+    BoardState,
+};
+
+impl BoardState {
+    /// Return a record for each legal move of `by`, generated piece by piece
+    ///
+    /// Moves are filtered by the pins on, and checks against, the King of `by`, rather than by
+    /// making each move. Note: a Pawn reaching the last rank has one record for each type of
+    /// promotion.
+    pub fn get_legal_move_records(&self, by: Colour) -> Vec<MoveRecord> {
+        let mut records = Vec::with_capacity(64);
+        let king = match self.find_king(by) {
+            Some(king) => king,
+            None => return records,
+        };
+        let king_square = square_index(king);
+        let occupied = self.bitboards.occupied();
+        let own = self.bitboards.colour(by);
+        let checkers = self.bitboards.attackers_to(king_square, !by, occupied);
+
+        self.add_king_moves(king, by, &mut records);
+        if checkers.count_ones() > 1 {
+            // only the King can escape a double check
+            return records;
+        }
+        let check_mask = match checkers {
+            0 => {
+                self.add_castles(king, by, &mut records);
+                !0
+            }
+            // take the checking piece, or block its line
+            checker => checker | between(king_square, checker.trailing_zeros() as usize),
+        };
+        let pins = self.get_pins(king_square, by);
+
+        for from in squares(own & !bit(king)) {
+            let piece = self.board[from.row][from.column].expect("Bitboards should match Board");
+            let from_square = square_index(from);
+            let pin_mask = pins
+                .iter()
+                .find(|&&(pinned, _)| pinned == from_square)
+                .map_or(!0, |&(_, line)| line);
+            let destinations = match piece.piece_type {
+                Pawn => {
+                    self.add_en_passant(from, king, by, &mut records);
+                    self.get_pawn_destinations(from, by)
+                }
+                piece_type => bitboard::attacks(piece_type, by, from_square, occupied) & !own,
+            };
+            for to in squares(destinations & check_mask & pin_mask) {
+                self.add_moves(Move::new(from, to), piece, &mut records);
+            }
+        }
+        records
+    }
+
+    /// Return each piece of `by` that is pinned to its King on `king_square`, with the squares
+    /// it may move to without leaving the line of the pin
+    fn get_pins(&self, king_square: usize, by: Colour) -> Vec<(usize, Bitboard)> {
+        let their_pieces = self.bitboards.colour(!by);
+        let queens = self.bitboards.pieces(Queen, !by);
+        let snipers = (bitboard::rook_attacks(king_square, their_pieces)
+            & (self.bitboards.pieces(Rook, !by) | queens))
+            | (bitboard::bishop_attacks(king_square, their_pieces)
+                & (self.bitboards.pieces(Bishop, !by) | queens));
+        squares(snipers)
+            .filter_map(|sniper| {
+                let sniper_square = square_index(sniper);
+                let line = between(king_square, sniper_square);
+                let blockers = line & self.bitboards.occupied();
+                if blockers.count_ones() == 1 && blockers & self.bitboards.colour(by) != 0 {
+                    Some((blockers.trailing_zeros() as usize, line | bit(sniper)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Return the squares that the Pawn of `by` on `from` may push to or take on (not counting
+    /// en-passant)
+    fn get_pawn_destinations(&self, from: Coordinate, by: Colour) -> Bitboard {
+        let occupied = self.bitboards.occupied();
+        let forward = |row: RowIndex| {
+            let row = row as i8 + if by == White { -1 } else { 1 };
+            match row {
+                0..=7 => Some(RowIndex::from(row as usize)),
+                _ => None,
+            }
+        };
+        let mut pushes = 0;
+        if let Some(row) = forward(from.row) {
+            let one_step = Coordinate {
+                row,
+                column: from.column,
+            };
+            pushes = bit(one_step) & !occupied;
+            if pushes != 0 && from.row == by.home_pawn_rank() {
+                if let Some(row) = forward(row) {
+                    pushes |= bit(Coordinate {
+                        row,
+                        column: from.column,
+                    }) & !occupied;
+                }
+            }
+        }
+        pushes | (bitboard::pawn_attacks(square_index(from), by) & self.bitboards.colour(!by))
+    }
+
+    /// Add the record for moving `piece` from `m.from` to `m.to`, or one for each promotion
+    fn add_moves(&self, m: Move, piece: Piece, records: &mut Vec<MoveRecord>) {
+        let taken = self.board[m.to.row][m.to.column];
+        if piece.piece_type == Pawn && m.to.row == (!piece.colour).home_rank() {
+            records.extend(PieceType::get_promotion_types().iter().map(|&to| {
+                MoveRecord::PawnPromotion {
+                    m: m.with_promotion(to),
+                    to,
+                    taken,
+                }
+            }));
+            return;
+        }
+        let first_move = !piece.has_moved;
+        records.push(match taken {
+            Some(taken) => MoveRecord::TakeMove {
+                m,
+                taken,
+                taken_from: m.to,
+                first_move,
+            },
+            None => MoveRecord::SimpleMove { m, first_move },
+        });
+    }
+
+    fn add_king_moves(&self, king: Coordinate, by: Colour, records: &mut Vec<MoveRecord>) {
+        let king_square = square_index(king);
+        // the King does not block attacks along the line it moves away on
+        let occupied = self.bitboards.occupied() & !bit(king);
+        let piece = self.board[king.row][king.column].expect("Bitboards should match Board");
+        let destinations = bitboard::king_attacks(king_square) & !self.bitboards.colour(by);
+        for to in squares(destinations) {
+            if self.bitboards.attackers_to(square_index(to), !by, occupied) == 0 {
+                self.add_moves(Move::new(king, to), piece, records);
+            }
+        }
+    }
+
+    /// Add the legal castles of `by`, whose King is on `king` and not in check
+    fn add_castles(&self, king: Coordinate, by: Colour, records: &mut Vec<MoveRecord>) {
+        let rank = by.home_rank();
+        if king.row != rank {
+            return;
+        }
+        for &direction in [UpFile, DownFile].iter() {
+            let rook_from = match self.castling_rights.get(by, direction) {
+                Some(column) => Coordinate { row: rank, column },
+                None => continue,
+            };
+            if !matches!(
+                self.board[rook_from.row][rook_from.column],
+                Some(Piece { piece_type: Rook, colour, .. }) if colour == by
+            ) {
+                continue;
+            }
+            let (king_column, rook_column) = match direction {
+                UpFile => (G, F),
+                DownFile => (C, D),
+            };
+            let king_move = Move::new(
+                king,
+                Coordinate {
+                    row: rank,
+                    column: king_column,
+                },
+            );
+            let rook_move = Move::new(
+                rook_from,
+                Coordinate {
+                    row: rank,
+                    column: rook_column,
+                },
+            );
+            let lifted = bit(king_move.from) | bit(rook_move.from);
+            let occupied = self.bitboards.occupied() & !lifted;
+            let (king_from, king_to) = (square_index(king_move.from), square_index(king_move.to));
+            let (rook_from, rook_to) = (square_index(rook_move.from), square_index(rook_move.to));
+            let path = between(king_from, king_to)
+                | bit(king_move.to)
+                | between(rook_from, rook_to)
+                | bit(rook_move.to);
+            if path & occupied != 0 {
+                continue;
+            }
+            // the King may not pass through check, nor end in check once the Rook has moved
+            let passes_through_check = squares(between(king_from, king_to)).any(|square| {
+                self.bitboards
+                    .attackers_to(square_index(square), !by, occupied)
+                    != 0
+            });
+            let ends_in_check =
+                self.bitboards
+                    .attackers_to(king_to, !by, occupied | bit(rook_move.to))
+                    != 0;
+            if !passes_through_check && !ends_in_check {
+                records.push(MoveRecord::CastleMove {
+                    rook_move,
+                    king_move,
+                });
+            }
+        }
+    }
+
+    /// Add the en-passant capture by the Pawn of `by` on `from`, if there is a legal one
+    fn add_en_passant(
+        &self,
+        from: Coordinate,
+        king: Coordinate,
+        by: Colour,
+        records: &mut Vec<MoveRecord>,
+    ) {
+        let target = match self.en_passant_availability {
+            Some(target) if by == self.current_player => target,
+            _ => return,
+        };
+        if bitboard::pawn_attacks(square_index(from), by) & bit(target) == 0 {
+            return;
+        }
+        let taken_from = Coordinate {
+            row: from.row,
+            column: target.column,
+        };
+        let taken = match self.board[taken_from.row][taken_from.column] {
+            Some(taken) if taken.piece_type == Pawn && taken.colour != by => taken,
+            _ => return,
+        };
+        // two pieces leave the line of the King, so make the capture on the bitboards to check it
+        let pawn = Piece::new(Pawn, by);
+        let mut bitboards = self.bitboards;
+        bitboards.toggle(pawn, from);
+        bitboards.toggle(pawn, target);
+        bitboards.toggle(taken, taken_from);
+        if !bitboards.is_attacked(square_index(king), !by) {
+            records.push(MoveRecord::TakeMove {
+                m: Move::new(from, target),
+                taken,
+                taken_from,
+                first_move: false,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{
+        coordinates::{ColumnIndex::*, Coordinate, Move, RowIndex::*},
+        BoardState,
+    };
+    use std::str::FromStr;
+
+    fn legal_moves(fen: &str) -> Vec<Move> {
+        let mut board_state = BoardState::from_str(fen).unwrap();
+        let player = board_state.get_next_player();
+        board_state.get_legal_moves(player)
+    }
+
+    #[test]
+    fn pinned_pieces_stay_on_the_line_of_the_pin() {
+        // the Rook is pinned along the file, and the Bishop along the diagonal
+        let moves = legal_moves("k3r3/8/8/8/7b/4R3/5B2/4K3 w - - 0 1");
+        let e3 = Coordinate { row: _3, column: E };
+        let f2 = Coordinate { row: _2, column: F };
+        assert!(moves
+            .iter()
+            .filter(|m| m.from == e3)
+            .all(|m| m.to.column == E));
+        assert_eq!(moves.iter().filter(|m| m.from == e3).count(), 6);
+        assert_eq!(
+            moves.iter().filter(|m| m.from == f2).collect::<Vec<_>>(),
+            vec![
+                &Move::new(f2, Coordinate { row: _4, column: H }),
+                &Move::new(f2, Coordinate { row: _3, column: G })
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_king_moves_in_double_check() {
+        let moves = legal_moves("4k3/8/8/8/1b6/3Q4/8/r3K3 w - - 0 1");
+        assert!(moves
+            .iter()
+            .all(|m| m.from == Coordinate { row: _1, column: E }));
+        assert!(!moves.is_empty());
+    }
+
+    #[test]
+    fn check_must_be_answered() {
+        // a Knight's check cannot be blocked, and the King cannot castle out of it
+        let moves = legal_moves("4k3/8/8/8/8/3n4/8/4K2R w K - 0 1");
+        let e1 = Coordinate { row: _1, column: E };
+        assert_eq!(moves.len(), 4);
+        assert!(moves.iter().all(|m| m.from == e1));
+        // the Knight can be taken, or the check blocked
+        let moves = legal_moves("4k3/8/8/8/8/3n4/8/3QK3 w - - 0 1");
+        assert!(moves.contains(&Move::new(
+            Coordinate { row: _1, column: D },
+            Coordinate { row: _3, column: D }
+        )));
+        let moves = legal_moves("4k3/8/8/8/8/8/8/r2NK3 w - - 0 1");
+        assert!(moves.iter().all(|m| m.from == e1 || m.to.row == _1));
+    }
+
+    #[test]
+    fn en_passant_that_exposes_the_king_is_illegal() {
+        let moves = legal_moves("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1");
+        assert!(!moves.contains(&Move::new(
+            Coordinate { row: _5, column: E },
+            Coordinate { row: _6, column: D }
+        )));
+        let moves = legal_moves("8/8/8/K2pP3/8/8/8/7k w - d6 0 1");
+        assert!(moves.contains(&Move::new(
+            Coordinate { row: _5, column: E },
+            Coordinate { row: _6, column: D }
+        )));
+    }
+}
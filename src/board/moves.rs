@@ -1,25 +1,68 @@
-use std::ops::Deref;
-
 use super::{
     coordinates::{ColumnIndex, Coordinate, Move, RowIndex},
     grid::Board,
     piece::{
-        Colour, Piece,
-        PieceType::{self, Pawn},
+        Colour::{self, *},
+        Piece,
+        PieceType::{self, *},
     },
 };
-use enum_map::EnumMap;
+use enum_map::{Enum, EnumMap};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
+
+use CastleDirection::*;
+use ColumnIndex::*;
+use MoveRecord::*;
+
+/// The files of the Rooks that each player may still castle with, in each direction
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct CastlingRights(EnumMap<Colour, EnumMap<CastleDirection, Option<ColumnIndex>>>);
+
+impl CastlingRights {
+    /// Return the castling rights at the start of a standard game
+    pub fn standard() -> Self {
+        let mut castling_rights = Self::default();
+        for &colour in [White, Black].iter() {
+            castling_rights.set(colour, UpFile, Some(H));
+            castling_rights.set(colour, DownFile, Some(A));
+        }
+        castling_rights
+    }
+
+    /// Return the file of the Rook that `colour` may castle with in `direction`, if any
+    pub fn get(&self, colour: Colour, direction: CastleDirection) -> Option<ColumnIndex> {
+        self.0[colour][direction]
+    }
 
-type CastlingAvailabilityData = EnumMap<Colour, EnumMap<ColumnIndex, bool>>;
+    pub fn set(&mut self, colour: Colour, direction: CastleDirection, file: Option<ColumnIndex>) {
+        self.0[colour][direction] = file;
+    }
 
-#[derive(Debug, PartialEq)]
-pub struct CastlingAvailability(pub CastlingAvailabilityData);
+    /// Return true iff `colour` may castle in either direction
+    pub fn can_castle(&self, colour: Colour) -> bool {
+        self.0[colour].values().any(Option::is_some)
+    }
 
-impl Deref for CastlingAvailability {
-    type Target = CastlingAvailabilityData;
+    /// Remove the rights of `colour` to castle in both directions (i.e. when the King moves)
+    pub fn remove_all(&mut self, colour: Colour) {
+        self.0[colour] = Default::default();
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    /// Remove any right to castle with a Rook on `square` (i.e. when it moves or is taken)
+    pub fn remove_rook_at(&mut self, square: Coordinate) {
+        for &colour in [White, Black].iter() {
+            if square.row == colour.home_rank() {
+                for file in self.0[colour].values_mut() {
+                    if *file == Some(square.column) {
+                        *file = None;
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -55,36 +98,182 @@ pub enum MoveRecord {
     },
 }
 
+impl MoveRecord {
+    /// Return the move a player makes to make this record
+    ///
+    /// This is the same as the move in the record, except for castles, which are given as the
+    /// King moving two squares, or as the King taking its own Rook in Chess960.
+    pub fn get_move(&self, chess960: bool) -> Move {
+        match *self {
+            SimpleMove { m, .. } | TakeMove { m, .. } | PawnPromotion { m, .. } => m,
+            CastleMove {
+                rook_move,
+                king_move,
+            } if chess960 => Move::new(king_move.from, rook_move.from),
+            CastleMove { king_move, .. } => king_move,
+        }
+    }
+}
+
+/// The reason that a move cannot be made
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MoveError {
+    EmptySquare,
+    OpponentsPiece,
+    NotMoved,
+    TakeOwnPiece,
+    /// a piece stands in the way of the move
+    Blocked {
+        piece: Piece,
+        at: Coordinate,
+    },
+    /// the move would leave the King in check
+    Check,
+
+    PromotionNotAllowed,
+    PromotionRequired,
+    InvalidPromotion(PieceType),
+
+    PawnJump,
+    PawnDoubleMoveNotOnStartRow,
+    PawnTooFar,
+    PawnBackwards,
+    PawnTakeNotDiagonal,
+    PawnHorizontal,
+    PawnDiagonalWithoutTaking,
+    PawnDiagonalTooFar,
+    PawnDiagonalBackwards,
+    PawnInvalidMove,
+    /// the piece cannot move in this pattern (for pieces other than Pawns)
+    InvalidPattern(PieceType),
+
+    CastleKingMoved,
+    CastleRightsLost,
+    CastleNoRook,
+    CastleMustTakeRook,
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::EmptySquare => write!(f, "Cannot move an empty square"),
+            MoveError::OpponentsPiece => write!(f, "Cannot move opponent's piece"),
+            MoveError::NotMoved => write!(f, "Must move piece"),
+            MoveError::TakeOwnPiece => write!(f, "Cannot take own piece"),
+            MoveError::Blocked { piece, at } => write!(
+                f,
+                "Cannot move here: blocked at ({:?}, {:?}), by {:?}",
+                at.row, at.column, piece
+            ),
+            MoveError::Check => write!(f, "Cannot move here: Check"),
+
+            MoveError::PromotionNotAllowed => {
+                write!(f, "Cannot promote unless moving a Pawn to the last rank")
+            }
+            MoveError::PromotionRequired => write!(f, "Must choose a piece to promote Pawn to"),
+            MoveError::InvalidPromotion(to) => write!(f, "Cannot promote Pawn to {:?}", to),
+
+            MoveError::PawnJump => write!(f, "Cannot jump with Pawn"),
+            MoveError::PawnDoubleMoveNotOnStartRow => {
+                write!(f, "Cannot double move Pawn unless on start row")
+            }
+            MoveError::PawnTooFar => write!(f, "Cannot move Pawn more than two squares"),
+            MoveError::PawnBackwards => write!(f, "Cannot move Pawn backwards"),
+            MoveError::PawnTakeNotDiagonal => write!(f, "Cannot take with Pawn unless diagonally"),
+            MoveError::PawnHorizontal => write!(f, "Cannot move Pawn horizontally"),
+            MoveError::PawnDiagonalWithoutTaking => {
+                write!(f, "Cannot move Pawn diagonally unless taking")
+            }
+            MoveError::PawnDiagonalTooFar => write!(f, "Cannot move Pawn here: too far"),
+            MoveError::PawnDiagonalBackwards => {
+                write!(f, "Cannot move Pawn here: must move forwards")
+            }
+            MoveError::PawnInvalidMove => write!(f, "Cannot move Pawn this way"),
+            MoveError::InvalidPattern(piece_type) => match piece_type {
+                Rook => write!(f, "Cannot move Rook here: not a straight line"),
+                Knight => write!(f, "Cannot move Knight here: not in L pattern"),
+                Bishop => write!(f, "Cannot move Bishop here: not a diagonal line"),
+                Queen => write!(f, "Cannot move Queen here: not in a line"),
+                King => write!(f, "Cannot move King more than one square"),
+                Pawn => write!(f, "Cannot move Pawn this way"),
+            },
+
+            MoveError::CastleKingMoved => write!(f, "Cannot castle: King has been moved"),
+            MoveError::CastleRightsLost => {
+                write!(
+                    f,
+                    "Cannot castle this way: King or target Rook has been moved"
+                )
+            }
+            MoveError::CastleNoRook => write!(f, "Cannot castle: no Rook found"),
+            MoveError::CastleMustTakeRook => {
+                write!(f, "Cannot castle this way: King must take the target Rook")
+            }
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+/// The direction of a castle: towards the H file (King-side) or the A file (Queen-side)
+#[derive(Debug, Enum, Copy, Clone, Eq, PartialEq)]
 pub enum CastleDirection {
     UpFile,
     DownFile,
 }
 
-// TODO: use for public API: check_move
+/// What a player does on their turn
+#[derive(Debug, Clone, PartialEq)]
 pub enum MoveSignal {
+    /// take back the last move of each player
     Undo,
+    /// resign the game
     Retire,
+    /// claim a draw by the fifty-move rule or threefold repetition (see `get_claimable_draw`)
     ForceDraw,
+    /// offer the other player a draw, which ends the game if they accept
     RequestDraw,
-    Castle(CastleDirection),
+    /// save the game to the given file, to be resumed later
+    Save(PathBuf),
     Make(Move),
-    PromotePawnTo(PieceType),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct MoveRecords {
     start_en_passant_availability: Option<Coordinate>,
+    start_halfmove_clock: u32,
     moves: Vec<MoveRecord>,
+    /// the halfmove clock after each of the moves
+    halfmove_clocks: Vec<u32>,
+    /// the hash key of the position each of the moves was made from
+    hash_keys: Vec<u64>,
+    /// the castling rights before each of the moves
+    castling_rights: Vec<CastlingRights>,
 }
 
 impl MoveRecords {
-    pub fn new(start_en_passant_availability: Option<Coordinate>) -> Self {
+    pub fn new(
+        start_en_passant_availability: Option<Coordinate>,
+        start_halfmove_clock: u32,
+    ) -> Self {
         Self {
             start_en_passant_availability,
+            start_halfmove_clock,
             moves: Default::default(),
+            halfmove_clocks: Default::default(),
+            hash_keys: Default::default(),
+            castling_rights: Default::default(),
         }
     }
 
+    /// Return the number of halfmoves since the last capture or Pawn advance
+    pub fn get_halfmove_clock(&self) -> u32 {
+        *self
+            .halfmove_clocks
+            .last()
+            .unwrap_or(&self.start_halfmove_clock)
+    }
+
     pub fn get_en_passant_availability(&self, board: &Board) -> Option<Coordinate> {
         if let Some(move_record) = self.moves.last() {
             match move_record {
@@ -118,21 +307,57 @@ impl MoveRecords {
         }
     }
 
+    /// Return the moves made so far, in order
+    pub fn get_records(&self) -> &[MoveRecord] {
+        &self.moves
+    }
+
     pub fn can_undo(&self) -> bool {
-        self.moves.len() > 0
+        !self.moves.is_empty()
     }
 
-    pub fn record_move(&mut self, record: MoveRecord) {
+    /// Count the earlier positions with the given hash key that can repeat the current one
+    ///
+    /// Only positions since the last irreversible move are considered.
+    pub fn count_repetitions(&self, hash_key: u64) -> usize {
+        let reversible_moves = self.get_halfmove_clock() as usize;
+        self.hash_keys
+            .iter()
+            .rev()
+            .take(reversible_moves)
+            .filter(|&&key| key == hash_key)
+            .count()
+    }
+
+    /// Note: a move is reversible iff it is neither a capture nor a Pawn move
+    pub fn record_move(
+        &mut self,
+        record: MoveRecord,
+        is_reversible: bool,
+        from_hash_key: u64,
+        from_castling_rights: CastlingRights,
+    ) {
+        let halfmove_clock = if is_reversible {
+            self.get_halfmove_clock() + 1
+        } else {
+            0
+        };
         self.moves.push(record);
+        self.halfmove_clocks.push(halfmove_clock);
+        self.hash_keys.push(from_hash_key);
+        self.castling_rights.push(from_castling_rights);
     }
 
-    pub fn pop_last_move(&mut self) -> Option<MoveRecord> {
-        self.moves.pop()
+    /// Remove the last move, returning it along with the castling rights from before it
+    pub fn pop_last_move(&mut self) -> Option<(MoveRecord, CastlingRights)> {
+        self.halfmove_clocks.pop();
+        self.hash_keys.pop();
+        self.moves.pop().zip(self.castling_rights.pop())
     }
 }
 
 impl Default for MoveRecords {
     fn default() -> Self {
-        Self::new(None)
+        Self::new(None, 0)
     }
 }
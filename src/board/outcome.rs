@@ -0,0 +1,169 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::piece::Colour;
+
+/// The reason a game was won
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WinReason {
+    Checkmate,
+    /// the other player resigning
+    Resignation,
+    /// the other player running out of time
+    Timeout,
+    /// the other player being judged lost by whoever runs the game (e.g. a tournament)
+    Adjudication,
+}
+
+/// The reason a game was drawn
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DrawReason {
+    Stalemate,
+    /// 50 moves by each player without a capture or Pawn move (must be claimed)
+    FiftyMoveRule,
+    /// 75 moves by each player without a capture or Pawn move (applies automatically)
+    SeventyFiveMoveRule,
+    /// the same position occurring three times (must be claimed)
+    ThreefoldRepetition,
+    /// the same position occurring five times (applies automatically)
+    FivefoldRepetition,
+    /// neither player having enough material left to checkmate
+    InsufficientMaterial,
+    /// one player offering a draw and the other accepting
+    Agreement,
+    /// one player running out of time when the other could not checkmate them
+    TimeoutVsInsufficientMaterial,
+    /// the game being judged drawn by whoever runs it (e.g. a tournament)
+    Adjudication,
+}
+
+/// The final result of a game, along with the rule that ended it
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameOutcome {
+    Win { winner: Colour, reason: WinReason },
+    Draw(DrawReason),
+}
+
+impl GameOutcome {
+    /// Return the winning player, or None for a draw
+    pub fn get_winner(&self) -> Option<Colour> {
+        match self {
+            GameOutcome::Win { winner, .. } => Some(*winner),
+            GameOutcome::Draw(_) => None,
+        }
+    }
+}
+
+impl Display for WinReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WinReason::Checkmate => write!(f, "checkmate"),
+            WinReason::Resignation => write!(f, "resignation"),
+            WinReason::Timeout => write!(f, "timeout"),
+            WinReason::Adjudication => write!(f, "adjudication"),
+        }
+    }
+}
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "stalemate"),
+            DrawReason::FiftyMoveRule => write!(f, "the fifty-move rule"),
+            DrawReason::SeventyFiveMoveRule => write!(f, "the seventy-five-move rule"),
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+            DrawReason::InsufficientMaterial => write!(f, "insufficient material"),
+            DrawReason::Agreement => write!(f, "agreement"),
+            DrawReason::TimeoutVsInsufficientMaterial => {
+                write!(f, "timeout with insufficient material to checkmate")
+            }
+            DrawReason::Adjudication => write!(f, "adjudication"),
+        }
+    }
+}
+
+impl Display for GameOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GameOutcome::Win { winner, reason } => write!(f, "{:?} wins by {}", winner, reason),
+            GameOutcome::Draw(reason) => write!(f, "Draw by {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawReason, GameOutcome, WinReason};
+    use crate::board::{piece::Colour::*, BoardState};
+    use std::str::FromStr;
+
+    #[test]
+    fn game_in_progress() {
+        assert_eq!(BoardState::default().outcome(), None);
+    }
+
+    #[test]
+    fn checkmate() {
+        let mut board_state =
+            BoardState::from_str("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        assert_eq!(
+            board_state.outcome(),
+            Some(GameOutcome::Win {
+                winner: Black,
+                reason: WinReason::Checkmate
+            })
+        );
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let drawn = [
+            "8/8/4k3/8/8/4K3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/6N1 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/5b2 w - - 0 1",
+            "8/8/4k3/3b4/8/4K3/8/5B2 w - - 0 1",
+        ];
+        for fen in drawn.iter() {
+            let mut board_state = BoardState::from_str(fen).unwrap();
+            assert!(board_state.is_insufficient_material(), "{}", fen);
+            assert_eq!(
+                board_state.outcome(),
+                Some(GameOutcome::Draw(DrawReason::InsufficientMaterial))
+            );
+        }
+        let not_drawn = [
+            "8/8/4k3/8/8/4K3/8/4P3 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/4R3 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/4NN2 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/4NB2 w - - 0 1",
+            "8/8/4k3/4b3/8/4K3/8/5B2 w - - 0 1",
+            "8/8/4k3/8/8/4K3/8/5Bn1 w - - 0 1",
+        ];
+        for fen in not_drawn.iter() {
+            let mut board_state = BoardState::from_str(fen).unwrap();
+            assert!(!board_state.is_insufficient_material(), "{}", fen);
+            assert_eq!(board_state.outcome(), None);
+        }
+    }
+
+    #[test]
+    fn mating_material() {
+        let board_state = BoardState::from_str("4k3/8/8/8/8/8/4P3/4K1N1 w - - 0 1").unwrap();
+        assert!(board_state.has_mating_material(White));
+        assert!(!board_state.has_mating_material(Black));
+        let board_state = BoardState::from_str("4k3/1n6/8/8/8/8/8/4KNB1 w - - 0 1").unwrap();
+        assert!(board_state.has_mating_material(White));
+        assert!(!board_state.has_mating_material(Black));
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut board_state = BoardState::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(board_state.is_stalemate());
+        assert_eq!(
+            board_state.outcome(),
+            Some(GameOutcome::Draw(DrawReason::Stalemate))
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use super::{coordinates::Move, moves::MoveRecord, BoardState};
+
+impl BoardState {
+    /// Return the number of leaf nodes in the tree of legal moves `depth` plies deep, for checking
+    /// the move generator against known counts
+    ///
+    /// Examples:
+    /// ```
+    /// use chess::board::BoardState;
+    /// let mut board_state = BoardState::default();
+    /// assert_eq!(board_state.perft(1), 20);
+    /// assert_eq!(board_state.perft(2), 400);
+    /// ```
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let records = self.get_legal_move_records(self.get_next_player());
+        if depth == 1 {
+            return records.len() as u64;
+        }
+        records
+            .into_iter()
+            .map(|record| self.perft_after(record, depth - 1))
+            .sum()
+    }
+
+    /// Return the perft count below each legal move, in the order they are generated
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return vec![];
+        }
+        self.get_legal_move_records(self.get_next_player())
+            .into_iter()
+            .map(|record| {
+                let m = record.get_move(self.chess960);
+                (m, self.perft_after(record, depth - 1))
+            })
+            .collect()
+    }
+
+    fn perft_after(&mut self, record: MoveRecord, depth: u32) -> u64 {
+        self.do_move(record);
+        let nodes = self.perft(depth);
+        self.undo_move();
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::BoardState;
+    use std::str::FromStr;
+
+    /// Check the perft counts of a position to each depth, and that they leave it unchanged
+    fn check_perft(fen: &str, expected: &[u64]) {
+        let mut board_state = BoardState::from_str(fen).unwrap();
+        let start_fen = board_state.to_fen();
+        for (depth, &nodes) in (1..).zip(expected.iter()) {
+            assert_eq!(
+                board_state.perft(depth),
+                nodes,
+                "{} to depth {}",
+                fen,
+                depth
+            );
+        }
+        assert_eq!(board_state.to_fen(), start_fen);
+        assert_eq!(board_state.hash_key(), board_state.compute_hash_key());
+    }
+
+    #[test]
+    fn initial_position() {
+        check_perft(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281],
+        );
+    }
+
+    #[test]
+    fn kiwipete() {
+        check_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn en_passant_pins() {
+        check_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        );
+    }
+
+    #[test]
+    fn promotions_and_castling_out_of_check() {
+        check_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+        check_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    #[test]
+    fn middlegame() {
+        check_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890],
+        );
+    }
+
+    #[test]
+    fn chess960() {
+        check_perft(
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            &[21, 528, 12189, 326672],
+        );
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let mut board_state = BoardState::default();
+        let divide = board_state.divide(3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 8902);
+    }
+}
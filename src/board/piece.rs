@@ -5,10 +5,10 @@ use std::{
     ops::Not,
 };
 
-use crate::board::coordinates::*;
+use crate::board::{coordinates::*, fen::FenError};
 use RowIndex::*;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -19,15 +19,22 @@ pub enum PieceType {
 }
 use PieceType::*;
 
-#[derive(Copy, Clone, Enum, Eq, PartialEq, Debug)]
+impl PieceType {
+    /// Return the types of piece that a Pawn may be promoted to, most valuable first
+    pub const fn get_promotion_types() -> &'static [PieceType; 4] {
+        const PROMOTION_TYPES: [PieceType; 4] = [Queen, Rook, Bishop, Knight];
+        &PROMOTION_TYPES
+    }
+}
+
+#[derive(Copy, Clone, Enum, Eq, PartialEq, Debug, Default)]
 pub enum Colour {
     Black,
+    #[default]
     White,
 }
 use Colour::*;
 
-use super::moves::CastlingAvailability;
-
 impl Not for Colour {
     type Output = Self;
 
@@ -39,12 +46,6 @@ impl Not for Colour {
     }
 }
 
-impl Default for Colour {
-    fn default() -> Self {
-        White
-    }
-}
-
 impl Colour {
     pub fn home_rank(&self) -> RowIndex {
         match self {
@@ -70,34 +71,21 @@ pub struct Piece {
 
 pub trait Movable {
     fn moved(self, moved: bool) -> Self;
-    fn guess_and_set_is_moved(
-        &mut self,
-        rank: RowIndex,
-        file: ColumnIndex,
-        castling_availability: &CastlingAvailability,
-    );
+    fn guess_and_set_is_moved(&mut self, rank: RowIndex);
 }
 
 impl Movable for Option<Piece> {
     fn moved(self, moved: bool) -> Self {
-        self.and_then(|mut piece| {
+        self.map(|mut piece| {
             piece.has_moved = moved;
-            Some(piece)
+            piece
         })
     }
 
-    fn guess_and_set_is_moved(
-        &mut self,
-        rank: RowIndex,
-        file: ColumnIndex,
-        castling_availability: &CastlingAvailability,
-    ) {
+    /// Note: castling does not depend on this, since it uses the explicit CastlingRights
+    fn guess_and_set_is_moved(&mut self, rank: RowIndex) {
         if let Some(piece) = self {
             match piece.piece_type {
-                Rook => {
-                    // assume a Rook has moved iff it is not available to castle (in principal)
-                    piece.has_moved = !castling_availability[piece.colour][file];
-                }
                 Pawn => {
                     // assume a Pawn has moved iff it is not on its home rank
                     piece.has_moved = piece.colour.home_pawn_rank() != rank;
@@ -110,7 +98,9 @@ impl Movable for Option<Piece> {
 }
 
 impl Piece {
-    pub fn from_char(c: char) -> Result<Option<Self>, String> {
+    /// Return the piece with the given FEN letter or chess symbol, or None for an empty square
+    /// (e.g. '_')
+    pub fn from_char(c: char) -> Result<Option<Self>, FenError> {
         let square = match c {
             'P' | '♙' => Piece::new(Pawn, White),
             'R' | '♖' => Piece::new(Rook, White),
@@ -128,11 +118,27 @@ impl Piece {
 
             '_' | ' ' | '◻' | '◼' => return Ok(None),
 
-            _ => return Err(format!("Invalid character for square {}", c)),
+            _ => return Err(FenError::InvalidPiece(c)),
         };
         Ok(Some(square))
     }
 
+    /// Return the letter for this piece in FEN (upper-case for White)
+    pub fn to_char(&self) -> char {
+        let c = match self.piece_type {
+            Pawn => 'p',
+            Rook => 'r',
+            Knight => 'n',
+            Bishop => 'b',
+            Queen => 'q',
+            King => 'k',
+        };
+        match self.colour {
+            White => c.to_ascii_uppercase(),
+            Black => c,
+        }
+    }
+
     pub fn new(piece_type: PieceType, colour: Colour) -> Self {
         Self {
             piece_type,
@@ -0,0 +1,385 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::parsing::parse_promotion_type;
+
+use super::{
+    coordinates::{ColumnIndex, Coordinate, Move, RowIndex},
+    moves::{MoveError, MoveRecord},
+    piece::{
+        Colour::*,
+        Piece,
+        PieceType::{self, *},
+    },
+    BoardState,
+};
+
+/// The reason that a move in Standard Algebraic Notation could not be read
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SanError {
+    /// the input is not written in SAN
+    InvalidSyntax(String),
+    /// no legal move in the position matches the input
+    NoMatchingMove(String),
+    /// more than one legal move matches the input, with the SAN of each
+    AmbiguousMove {
+        san: String,
+        candidates: Vec<String>,
+    },
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::InvalidSyntax(san) => write!(f, "Invalid move {} (not in SAN)", san),
+            SanError::NoMatchingMove(san) => write!(f, "Illegal move {}", san),
+            SanError::AmbiguousMove { san, candidates } => write!(
+                f,
+                "Ambiguous move {} (could be {})",
+                san,
+                candidates.join(" or ")
+            ),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+/// Return the lower-case letter of `column`
+fn file_char(column: ColumnIndex) -> char {
+    (b'a' + column as u8) as char
+}
+
+/// Return the upper-case letter of `piece_type`
+fn piece_char(piece_type: PieceType) -> char {
+    Piece::new(piece_type, White).to_char()
+}
+
+impl BoardState {
+    /// Return the Standard Algebraic Notation of `m`, a move by the next player
+    ///
+    /// Examples:
+    /// ```
+    /// use chess::board::{coordinates::{ColumnIndex::*, Coordinate, Move, RowIndex::*}, BoardState};
+    /// let mut board_state = BoardState::default();
+    /// let m = Move::new(Coordinate { row: _1, column: G }, Coordinate { row: _3, column: F });
+    /// assert_eq!(board_state.to_san(m).unwrap(), "Nf3");
+    /// ```
+    pub fn to_san(&mut self, m: Move) -> Result<String, MoveError> {
+        self.is_legal_move(m)?;
+        let record = self.get_move_result(m, self.get_next_player())?;
+        let legal_records = self.get_legal_move_records(self.get_next_player());
+        Ok(self.get_san(record, &legal_records))
+    }
+
+    /// Return the legal move of the next player given in Standard Algebraic Notation
+    ///
+    /// Common variants are accepted: a missing or ':' capture mark, castling with zeros, a
+    /// promotion without '=', and trailing check marks and annotations (e.g. "!?").
+    ///
+    /// Examples:
+    /// ```
+    /// use chess::board::{coordinates::{ColumnIndex::*, Coordinate, Move, RowIndex::*}, BoardState};
+    /// let mut board_state = BoardState::default();
+    /// assert_eq!(
+    ///     board_state.from_san("e4!").unwrap(),
+    ///     Move::new(Coordinate { row: _2, column: E }, Coordinate { row: _4, column: E })
+    /// );
+    /// ```
+    pub fn from_san(&mut self, san: &str) -> Result<Move, SanError> {
+        let invalid = || SanError::InvalidSyntax(String::from(san));
+        let text = san.trim().trim_end_matches(['!', '?', '+', '#']);
+        let text = text.strip_suffix("e.p.").unwrap_or(text).trim_end();
+
+        let legal_records = self.get_legal_move_records(self.get_next_player());
+        let candidates: Vec<MoveRecord> = match text {
+            "O-O" | "0-0" | "o-o" | "O-O-O" | "0-0-0" | "o-o-o" => {
+                let up_file = text.len() == 3;
+                legal_records
+                    .iter()
+                    .filter(|record| match record {
+                        MoveRecord::CastleMove {
+                            rook_move,
+                            king_move,
+                        } => (rook_move.from.column > king_move.from.column) == up_file,
+                        _ => false,
+                    })
+                    .copied()
+                    .collect()
+            }
+            _ => {
+                let mut chars: Vec<char> = text.chars().collect();
+                let promotion = match chars.last() {
+                    Some(&c) if !c.is_ascii_digit() => {
+                        chars.pop();
+                        if chars.last() == Some(&'=') {
+                            chars.pop();
+                        }
+                        Some(parse_promotion_type(&c.to_string()).map_err(|_| invalid())?)
+                    }
+                    _ => None,
+                };
+                if chars.len() < 2 {
+                    return Err(invalid());
+                }
+                let to: String = chars.split_off(chars.len() - 2).into_iter().collect();
+                let to: Coordinate = to.parse().map_err(|_| invalid())?;
+                let piece_type = match chars.first() {
+                    Some('K') => King,
+                    Some('Q') => Queen,
+                    Some('R') => Rook,
+                    Some('B') => Bishop,
+                    Some('N') => Knight,
+                    Some('P') => Pawn,
+                    _ => {
+                        // there is no piece letter to remove
+                        chars.insert(0, 'P');
+                        Pawn
+                    }
+                };
+                chars.remove(0);
+                if matches!(chars.last(), Some('x') | Some('X') | Some(':') | Some('-')) {
+                    chars.pop();
+                }
+                // the file and rank (either, both or neither) that the piece moves from
+                let (mut file, mut rank) = (None, None);
+                for c in chars {
+                    match c {
+                        'a'..='h' if file.is_none() && rank.is_none() => {
+                            file = ColumnIndex::parse(c).ok()
+                        }
+                        '1'..='8' if rank.is_none() => rank = RowIndex::parse(c).ok(),
+                        _ => return Err(invalid()),
+                    }
+                }
+
+                legal_records
+                    .iter()
+                    .filter(|record| {
+                        let (m, record_promotion) = match **record {
+                            MoveRecord::CastleMove { .. } => return false,
+                            MoveRecord::PawnPromotion { m, to, .. } => (m, Some(to)),
+                            MoveRecord::SimpleMove { m, .. } | MoveRecord::TakeMove { m, .. } => {
+                                (m, None)
+                            }
+                        };
+                        m.to == to
+                            && self.board[m.from.row][m.from.column]
+                                .is_some_and(|piece| piece.piece_type == piece_type)
+                            && file.is_none_or(|file| m.from.column == file)
+                            && rank.is_none_or(|rank| m.from.row == rank)
+                            && promotion.is_none_or(|promotion| record_promotion == Some(promotion))
+                    })
+                    .copied()
+                    .collect()
+            }
+        };
+
+        match candidates[..] {
+            [] => Err(SanError::NoMatchingMove(String::from(san))),
+            [record] => Ok(record.get_move(self.chess960)),
+            _ => Err(SanError::AmbiguousMove {
+                san: String::from(san),
+                candidates: candidates
+                    .into_iter()
+                    .map(|record| self.get_san(record, &legal_records))
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Return the SAN of the legal move `record`, disambiguated from the other `legal_records`
+    fn get_san(&mut self, record: MoveRecord, legal_records: &[MoveRecord]) -> String {
+        let mut san = String::new();
+        match record {
+            MoveRecord::CastleMove {
+                rook_move,
+                king_move,
+            } => san.push_str(if rook_move.from.column > king_move.from.column {
+                "O-O"
+            } else {
+                "O-O-O"
+            }),
+            _ => {
+                let m = record.get_move(self.chess960);
+                let piece = self.board[m.from.row][m.from.column]
+                    .expect("A legal move should have a piece to move");
+                let is_capture = matches!(
+                    record,
+                    MoveRecord::TakeMove { .. } | MoveRecord::PawnPromotion { taken: Some(_), .. }
+                );
+                if piece.piece_type == Pawn {
+                    if is_capture {
+                        san.push(file_char(m.from.column));
+                    }
+                } else {
+                    san.push(piece_char(piece.piece_type));
+                    // the squares of the other pieces of the same type that can move to m.to
+                    let rivals: Vec<Coordinate> = legal_records
+                        .iter()
+                        .filter(|other| !matches!(other, MoveRecord::CastleMove { .. }))
+                        .map(|other| other.get_move(self.chess960))
+                        .filter(|other| {
+                            other.to == m.to
+                                && other.from != m.from
+                                && self.board[other.from.row][other.from.column]
+                                    .is_some_and(|other| other.piece_type == piece.piece_type)
+                        })
+                        .map(|other| other.from)
+                        .collect();
+                    if !rivals.is_empty() {
+                        if rivals.iter().all(|other| other.column != m.from.column) {
+                            san.push(file_char(m.from.column));
+                        } else if rivals.iter().all(|other| other.row != m.from.row) {
+                            san.push_str(&m.from.row.to_string());
+                        } else {
+                            san.push_str(&m.from.to_string());
+                        }
+                    }
+                }
+                if is_capture {
+                    san.push('x');
+                }
+                san.push_str(&m.to.to_string());
+                if let MoveRecord::PawnPromotion { to, .. } = record {
+                    san.push('=');
+                    san.push(piece_char(to));
+                }
+            }
+        }
+
+        self.do_move(record);
+        if self.is_checkmate() {
+            san.push('#');
+        } else if self.is_in_check(self.get_next_player()) {
+            san.push('+');
+        }
+        self.undo_move();
+        san
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SanError;
+    use crate::board::BoardState;
+    use std::str::FromStr;
+
+    /// Check that each SAN is read as a legal move, and written back the same
+    fn check_round_trip(fen: &str, sans: &[&str]) {
+        let mut board_state = BoardState::from_str(fen).unwrap();
+        for &san in sans {
+            let m = board_state.from_san(san).unwrap();
+            assert_eq!(board_state.to_san(m).unwrap(), san, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn piece_moves_and_captures() {
+        check_round_trip(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[
+                "O-O", "O-O-O", "Nxf7", "Qxf6", "dxe6", "gxh3", "Bxa6", "a3", "a4", "Nb1", "Kd1",
+            ],
+        );
+    }
+
+    #[test]
+    fn disambiguation() {
+        // Knights on b1 and f3 (by file), Rooks on a1 and a5 (by rank), Queens on a8, c8 and
+        // a6 (by both)
+        check_round_trip(
+            "Q1Q5/8/Q7/R7/8/5N2/6k1/RN2K3 w - - 0 1",
+            &[
+                "Nbd2", "Nfd2+", "R1a3", "R5a3", "Qa8b7", "Qcb7", "Q6b7", "Kd1",
+            ],
+        );
+    }
+
+    #[test]
+    fn promotions_checks_and_mates() {
+        check_round_trip(
+            "1r5k/P5pp/8/8/8/8/8/4K2R w K - 0 1",
+            &["a8=Q", "axb8=N", "Rh6", "O-O", "Kf1"],
+        );
+        check_round_trip(
+            "6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1",
+            &["Ra8#", "Ra7", "O-O-O"],
+        );
+        check_round_trip("4k3/8/8/8/8/8/8/4K2R w K - 0 1", &["Rh8+", "O-O"]);
+    }
+
+    #[test]
+    fn chess960_castling() {
+        check_round_trip(
+            "r3k2r/8/8/8/8/8/8/1R2K1R1 w GBha - 0 1",
+            &["O-O", "O-O-O", "Rb8+", "Kd2"],
+        );
+    }
+
+    #[test]
+    fn forgiving_input() {
+        let mut board_state = BoardState::from_str(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        for &(forgiven, san) in [
+            ("0-0", "O-O"),
+            ("0-0-0+", "O-O-O"),
+            ("Nf7", "Nxf7"),
+            ("N:f7!?", "Nxf7"),
+            ("de6", "dxe6"),
+            ("Ne5xf7", "Nxf7"),
+            ("Pa3", "a3"),
+        ]
+        .iter()
+        {
+            let m = board_state.from_san(forgiven).unwrap();
+            assert_eq!(board_state.from_san(san).unwrap(), m, "{}", forgiven);
+        }
+        let mut board_state = BoardState::from_str("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board_state.from_san("a8Q").unwrap(),
+            board_state.from_san("a8=Q").unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_ambiguous_and_illegal() {
+        let mut board_state = BoardState::from_str("4k3/8/8/8/8/5N2/P7/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board_state.from_san("Nd2"),
+            Err(SanError::AmbiguousMove {
+                san: String::from("Nd2"),
+                candidates: vec![String::from("Nfd2"), String::from("Nbd2")]
+            })
+        );
+        assert_eq!(
+            board_state.from_san("Nd4"),
+            Ok(board_state.from_san("Nfd4").unwrap())
+        );
+        assert_eq!(
+            board_state.from_san("a5"),
+            Err(SanError::NoMatchingMove(String::from("a5")))
+        );
+        assert_eq!(
+            board_state.from_san("O-O"),
+            Err(SanError::NoMatchingMove(String::from("O-O")))
+        );
+        for &invalid in ["", "Z", "Ni9", "e4e", "Nbb1d2"].iter() {
+            assert_eq!(
+                board_state.from_san(invalid),
+                Err(SanError::InvalidSyntax(String::from(invalid)))
+            );
+        }
+        let mut board_state = BoardState::from_str("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        match board_state.from_san("a8") {
+            Err(SanError::AmbiguousMove { candidates, .. }) => {
+                assert_eq!(candidates, vec!["a8=Q+", "a8=R+", "a8=B", "a8=N"])
+            }
+            result => panic!("Expected an ambiguous promotion, not {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::{
+    coordinates::{ColumnIndex, Coordinate},
+    piece::{Colour, Piece},
+};
+
+/// Pseudo-random keys used to hash each feature of a position
+pub struct ZobristKeys {
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    castling: [[u64; 8]; 2],
+    en_passant: [u64; 8],
+}
+
+/// One step of the SplitMix64 generator, returning the next state and output
+const fn split_mix_64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+impl ZobristKeys {
+    const fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let mut keys = ZobristKeys {
+            pieces: [[[0; 64]; 6]; 2],
+            black_to_move: 0,
+            castling: [[0; 8]; 2],
+            en_passant: [0; 8],
+        };
+        let mut colour = 0;
+        while colour < 2 {
+            let mut piece_type = 0;
+            while piece_type < 6 {
+                let mut square = 0;
+                while square < 64 {
+                    let (next_state, key) = split_mix_64(state);
+                    state = next_state;
+                    keys.pieces[colour][piece_type][square] = key;
+                    square += 1;
+                }
+                piece_type += 1;
+            }
+            let mut file = 0;
+            while file < 8 {
+                let (next_state, key) = split_mix_64(state);
+                state = next_state;
+                keys.castling[colour][file] = key;
+                file += 1;
+            }
+            colour += 1;
+        }
+        let mut file = 0;
+        while file < 8 {
+            let (next_state, key) = split_mix_64(state);
+            state = next_state;
+            keys.en_passant[file] = key;
+            file += 1;
+        }
+        let (_, key) = split_mix_64(state);
+        keys.black_to_move = key;
+        keys
+    }
+
+    pub fn piece(&self, piece: Piece, at: Coordinate) -> u64 {
+        let square = at.row as usize * 8 + at.column as usize;
+        self.pieces[piece.colour as usize][piece.piece_type as usize][square]
+    }
+
+    pub fn black_to_move(&self) -> u64 {
+        self.black_to_move
+    }
+
+    /// The key for the right of `colour` to castle with the Rook on `file`
+    pub fn castling(&self, colour: Colour, file: ColumnIndex) -> u64 {
+        self.castling[colour as usize][file as usize]
+    }
+
+    pub fn en_passant(&self, file: ColumnIndex) -> u64 {
+        self.en_passant[file as usize]
+    }
+}
+
+pub static ZOBRIST_KEYS: ZobristKeys = ZobristKeys::new(0x2545_F491_4F6C_DD1D);
@@ -1,94 +1,283 @@
 use crate::{
     board::{
         coordinates::{ColumnIndex, Coordinate, Move, RowIndex},
+        moves::{MoveError, MoveSignal},
+        outcome::GameOutcome,
         piece::{
             Colour::{self, *},
             Piece,
         },
         BoardState,
     },
+    clock::{format_time, Clock},
     display::Display,
-    parsing::parse_coordinate,
+    parsing::parse_promotion_type,
     Player,
 };
-use std::io::{self, Write};
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+/// The file the "save" command writes to if none is given
+const DEFAULT_SAVE_PATH: &str = "chess-save.pgn";
 
-struct CliDisplay {}
+const HELP: &str = "\
+Enter a move in SAN (e.g. Nf3, exd5, O-O, e8=Q) or coordinates (e.g. g1f3, e7e8q),
+or the square of a piece and then the square to move it to, or one of:
+  undo         take back your last move (and your opponent's since)
+  resign       resign the game
+  draw         claim a draw if the rules allow it, otherwise offer one
+  flip         show the board from the other side
+  fen          show the position in FEN
+  save [FILE]  save the game to FILE (chess-save.pgn if not given), to resume it later
+  moves [SQ]   list the legal moves (from the square SQ, if given)
+  help         show this message";
+
+/// Shows the board in the terminal, shared between a player and the game it is playing in
+#[derive(Clone, Default)]
+struct CliDisplay {
+    /// whether the board is shown from Black's side
+    flipped: Rc<Cell<bool>>,
+}
+
+impl CliDisplay {
+    fn flip(&self) {
+        self.flipped.set(!self.flipped.get());
+    }
+}
 
 impl Display for CliDisplay {
     fn get_unique_id(&self) -> u32 {
         1
     }
-    fn display_board(&self, board_state: &BoardState) {
+    fn display_board(&self, board_state: &BoardState, clock: Option<&Clock>) {
         print!("{}[2J", 27 as char);
-        pretty_print(&board_state);
+        pretty_print(board_state, self.flipped.get());
+        if let Some(clock) = clock {
+            println!(
+                "\nWhite {} | Black {}",
+                format_time(clock.get_remaining(White)),
+                format_time(clock.get_remaining(Black))
+            );
+        }
         println!("\n{:?}'s move\n", board_state.get_next_player());
     }
-    fn display_checkmate(&self, winner: Colour) {
-        println!("Checkmate!\n{:?} Wins", winner);
+    fn display_game_over(&self, outcome: GameOutcome) {
+        println!("Game over!\n{}", outcome);
+    }
+    fn display_warning(&self, warning: &str) {
+        eprintln!("{}", warning);
     }
 }
 
-pub struct InteractiveCliPlayer {}
+pub struct InteractiveCliPlayer {
+    display: CliDisplay,
+}
 
 impl InteractiveCliPlayer {
     pub fn new() -> Self {
-        InteractiveCliPlayer {}
+        InteractiveCliPlayer {
+            display: CliDisplay::default(),
+        }
     }
 }
 
-impl Player for InteractiveCliPlayer {
-    fn get_move(&self, board_state: &mut BoardState) -> io::Result<Move> {
-        fn get_coordinate(message: &str) -> io::Result<Coordinate> {
-            let mut buffer = String::new();
-            loop {
-                print!("{}", message);
-                io::stdout().flush()?;
-                io::stdin().read_line(&mut buffer)?;
-                let coordinates = parse_coordinate(buffer.trim_end());
-                buffer.clear();
-                match coordinates {
-                    Ok(c) => return Ok(c),
-                    Err(e) => {
-                        println!("Error: {}", e);
-                    }
+impl Default for InteractiveCliPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read a move in SAN (e.g. "Nf3" or "O-O") or coordinate notation (e.g. "e2e4" or "e7e8n"), the
+/// square of a piece followed by the square to move it to, or a command (see `HELP`), from the
+/// lines returned by `read_line` (given the prompt for each), showing the board on `display`
+///
+/// Note: while a piece is selected, the square of another piece selects that piece instead, and
+/// an empty line cancels the selection.
+fn read_move(
+    board_state: &mut BoardState,
+    clock: Option<&Clock>,
+    display: &CliDisplay,
+    read_line: &mut dyn FnMut(&str) -> io::Result<String>,
+) -> io::Result<MoveSignal> {
+    let player = board_state.get_next_player();
+    let mut selected: Option<Coordinate> = None;
+    loop {
+        let input = match selected {
+            Some(from) => read_line(&format!("Move {} to (or select another piece): ", from))?,
+            None => read_line("Your move (e.g. Nf3, e2e4, or a square to move from): ")?,
+        };
+        let input = input.trim();
+        if input.is_empty() {
+            selected = None;
+            continue;
+        }
+        let mut words = input.split_whitespace();
+        let command_result = match (words.next(), words.next(), words.next()) {
+            (Some("undo"), None, _) if board_state.get_move_records().len() >= 2 => {
+                return Ok(MoveSignal::Undo)
+            }
+            (Some("undo"), None, _) => Err(String::from("No moves to undo")),
+            (Some("resign"), None, _) => return Ok(MoveSignal::Retire),
+            (Some("draw"), None, _) => match board_state.get_claimable_draw() {
+                Some(_) => return Ok(MoveSignal::ForceDraw),
+                None => {
+                    println!("You offer a draw");
+                    return Ok(MoveSignal::RequestDraw);
                 }
+            },
+            (Some("flip"), None, _) => {
+                display.flip();
+                display.display_board(board_state, clock);
+                Ok(())
             }
-        }
-        let player = board_state.get_next_player();
-        let from = loop {
-            let possible_from = get_coordinate("Move from: ")?;
-            let possible_moves = board_state.get_legal_moves_from(possible_from, player);
-            if possible_moves.len() != 0 {
-                break possible_from;
-            } else {
-                println!(
-                    "Error: No moves available from {:?}{:?}",
-                    possible_from.column, possible_from.row
-                );
+            (Some("save"), path, None) => {
+                let path = PathBuf::from(path.unwrap_or(DEFAULT_SAVE_PATH));
+                println!("Saving the game to {}", path.display());
+                return Ok(MoveSignal::Save(path));
+            }
+            (Some("fen"), None, _) => {
+                println!("{}", board_state.to_fen());
+                Ok(())
+            }
+            (Some("moves"), from, None) => list_moves(board_state, from),
+            (Some("help"), None, _) => {
+                println!("{}", HELP);
+                Ok(())
             }
+            _ => Err(String::new()),
         };
-        let m = loop {
-            let to = get_coordinate("Move to: ")?;
-            let possible_move = Move { from, to };
-            match board_state.is_legal_move(possible_move) {
-                Ok(()) => {
-                    break possible_move;
+        match command_result {
+            Ok(()) => continue,
+            Err(error) if !error.is_empty() => {
+                println!("Error: {}", error);
+                continue;
+            }
+            Err(_) => {}
+        }
+        let result = if let Ok(square) = input.parse::<Coordinate>() {
+            let has_moves = !board_state.get_legal_moves_from(square, player).is_empty();
+            match (selected, has_moves) {
+                (_, true) => {
+                    selected = Some(square);
+                    continue;
                 }
-                Err(error) => println!("Error: {}", error),
+                (Some(from), false) => {
+                    with_promotion(board_state, Move::new(from, square), read_line)?
+                        .map_err(|error| error.to_string())
+                }
+                // a square without a piece that can move may be a Pawn move in SAN
+                (None, false) => board_state
+                    .from_san(input)
+                    .map_err(|_| format!("No moves available from {}", square)),
             }
+        } else if let Ok(m) = input.parse::<Move>() {
+            with_promotion(board_state, m, read_line)?.map_err(|error| error.to_string())
+        } else {
+            board_state
+                .from_san(input)
+                .map_err(|error| error.to_string())
         };
-        Ok(m)
+        match result {
+            Ok(m) => return Ok(MoveSignal::Make(m)),
+            Err(error) => println!("Error: {} (enter \"help\" for help)", error),
+        }
+    }
+}
+
+/// Print the legal moves in SAN, only those from the square `from` if given
+fn list_moves(board_state: &mut BoardState, from: Option<&str>) -> Result<(), String> {
+    let player = board_state.get_next_player();
+    let moves = match from {
+        Some(from) => {
+            let from = from.parse().map_err(|error| format!("{}", error))?;
+            board_state.get_legal_moves_from(from, player)
+        }
+        None => board_state.get_legal_moves(player),
+    };
+    let sans: Vec<String> = moves
+        .into_iter()
+        .map(|m| board_state.to_san(m).expect("A legal move should be legal"))
+        .collect();
+    if sans.is_empty() {
+        println!("No legal moves");
+    } else {
+        println!("{}", sans.join(" "));
+    }
+    Ok(())
+}
+
+/// Print `prompt` and return the next line of standard input
+fn read_stdin_line(prompt: &str) -> io::Result<String> {
+    let mut buffer = String::new();
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    if io::stdin().read_line(&mut buffer)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "No more input to read moves from",
+        ));
+    }
+    Ok(buffer)
+}
+
+/// Return `m`, asking for the type of piece to promote to if it needs one, if it is legal
+fn with_promotion(
+    board_state: &mut BoardState,
+    m: Move,
+    read_line: &mut dyn FnMut(&str) -> io::Result<String>,
+) -> io::Result<Result<Move, MoveError>> {
+    match board_state.is_legal_move(m) {
+        Err(MoveError::PromotionRequired) => {
+            let promotion_type = loop {
+                match parse_promotion_type(read_line("Promote to [Q/R/B/N]: ")?.trim()) {
+                    Ok(promotion_type) => break promotion_type,
+                    Err(error) => println!("Error: {}", error),
+                }
+            };
+            let m = m.with_promotion(promotion_type);
+            Ok(board_state.is_legal_move(m).map(|()| m))
+        }
+        result => Ok(result.map(|()| m)),
+    }
+}
+
+impl Player for InteractiveCliPlayer {
+    fn get_move(
+        &self,
+        board_state: &mut BoardState,
+        clock: Option<&Clock>,
+    ) -> io::Result<MoveSignal> {
+        read_move(board_state, clock, &self.display, &mut read_stdin_line)
+    }
+    fn accept_draw(&self, _: &mut BoardState) -> io::Result<bool> {
+        loop {
+            match read_stdin_line("Your opponent offers a draw. Accept? [y/n]: ")?
+                .trim()
+                .to_lowercase()
+                .as_str()
+            {
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => {}
+            }
+        }
     }
     fn get_display(&self) -> Box<dyn Display> {
-        Box::new(CliDisplay {})
+        Box::new(self.display.clone())
+    }
+    fn get_name(&self) -> String {
+        String::from("Human")
     }
 }
 
 fn square_colour(rank: RowIndex, file: ColumnIndex) -> Colour {
     let r = rank as u8;
     let f = file as u8;
-    if f % 2 ^ r % 2 == 0 {
+    if (f % 2) ^ (r % 2) == 0 {
         White
     } else {
         Black
@@ -106,18 +295,130 @@ fn print_square(square: Option<Piece>, rank: RowIndex, file: ColumnIndex) -> Str
     }
 }
 
-fn pretty_print(board_state: &BoardState) -> String {
+/// Note: the board is shown from White's side unless `flipped`
+fn pretty_print(board_state: &BoardState, flipped: bool) -> String {
+    let mut columns = ColumnIndex::get_columns().to_vec();
+    let mut rows = RowIndex::get_rows().to_vec();
+    if flipped {
+        columns.reverse();
+        rows.reverse();
+    }
     print!("  ");
-    for &column in ColumnIndex::get_columns() {
+    for &column in columns.iter() {
         print!(" {:?}", column);
     }
-    println!("");
-    for &rank in RowIndex::get_rows() {
+    println!();
+    for &rank in rows.iter() {
         print!("{:?}", rank);
-        for (file, square) in board_state.board[rank] {
+        for &file in columns.iter() {
+            let square = board_state.get_board()[rank][file];
             print!(" {}", print_square(square, rank, file));
         }
-        println!("");
+        println!();
     }
     String::from("")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{read_move, CliDisplay, InteractiveCliPlayer};
+    use crate::board::{moves::MoveSignal, BoardState};
+    use std::{io, path::PathBuf, str::FromStr};
+
+    /// Return the move read from the lines of `input`
+    fn read(fen: &str, input: &[&str]) -> io::Result<MoveSignal> {
+        let mut board_state = BoardState::from_str(fen).unwrap();
+        let mut lines = input.iter();
+        read_move(&mut board_state, None, &CliDisplay::default(), &mut |_| {
+            lines
+                .next()
+                .map(|&line| format!("{}\n", line))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+        })
+    }
+
+    fn make(m: &str) -> MoveSignal {
+        MoveSignal::Make(m.parse().unwrap())
+    }
+
+    #[test]
+    fn san_coordinates_and_squares() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(read(start, &["Nf3"]).unwrap(), make("g1f3"));
+        assert_eq!(read(start, &["e4"]).unwrap(), make("e2e4"));
+        assert_eq!(read(start, &["d2d4"]).unwrap(), make("d2d4"));
+        assert_eq!(read(start, &["b1", "c3"]).unwrap(), make("b1c3"));
+        let castles = "4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+        assert_eq!(read(castles, &["O-O"]).unwrap(), make("e1g1"));
+    }
+
+    #[test]
+    fn selection_can_be_changed_or_cancelled() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        // a Knight cannot reach e4, so another piece is selected instead of asking again forever
+        assert_eq!(
+            read(start, &["b1", "e4", "e2", "e4"]).unwrap(),
+            make("e2e4")
+        );
+        assert_eq!(read(start, &["b1", "g1", "f3"]).unwrap(), make("g1f3"));
+        assert_eq!(read(start, &["b1", "", "Nf3"]).unwrap(), make("g1f3"));
+        assert!(read(start, &["b1", "b2"]).is_err());
+    }
+
+    #[test]
+    fn promotions() {
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(read(fen, &["a7a8n"]).unwrap(), make("a7a8n"));
+        assert_eq!(read(fen, &["a8=R"]).unwrap(), make("a7a8r"));
+        assert_eq!(read(fen, &["a7a8", "x", "B"]).unwrap(), make("a7a8b"));
+        assert_eq!(read(fen, &["a7", "a8", "q"]).unwrap(), make("a7a8q"));
+    }
+
+    #[test]
+    fn commands() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(read(start, &["resign"]).unwrap(), MoveSignal::Retire);
+        assert_eq!(read(start, &["draw"]).unwrap(), MoveSignal::RequestDraw);
+        assert_eq!(
+            read(start, &["save game.pgn"]).unwrap(),
+            MoveSignal::Save(PathBuf::from("game.pgn"))
+        );
+        // there are no moves to take back, so the command is refused and another is read
+        assert_eq!(read(start, &["undo", "e4"]).unwrap(), make("e2e4"));
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        let mut board_state = BoardState::from_str(fen).unwrap();
+        board_state.try_move("g1f3".parse().unwrap()).unwrap();
+        board_state.try_move("b8c6".parse().unwrap()).unwrap();
+        let mut lines = ["fen", "moves", "moves b1", "moves z9", "help", "undo"].iter();
+        let signal = read_move(&mut board_state, None, &CliDisplay::default(), &mut |_| {
+            lines
+                .next()
+                .map(|&line| String::from(line))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+        });
+        assert_eq!(signal.unwrap(), MoveSignal::Undo);
+    }
+
+    #[test]
+    fn draws_are_claimed_when_the_rules_allow() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 100 80";
+        assert_eq!(read(fen, &["draw"]).unwrap(), MoveSignal::ForceDraw);
+    }
+
+    #[test]
+    fn flipping_the_board_is_per_player() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let (player, other_player) = (InteractiveCliPlayer::new(), InteractiveCliPlayer::new());
+        let mut board_state = BoardState::from_str(start).unwrap();
+        let mut lines = ["flip", "resign"].iter();
+        let signal = read_move(&mut board_state, None, &player.display, &mut |_| {
+            lines
+                .next()
+                .map(|&line| String::from(line))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+        });
+        assert_eq!(signal.unwrap(), MoveSignal::Retire);
+        assert!(player.display.flipped.get());
+        assert!(!other_player.display.flipped.get());
+    }
+}
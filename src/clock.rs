@@ -0,0 +1,548 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+
+use enum_map::{enum_map, EnumMap};
+
+use crate::board::piece::Colour;
+
+/// Time given back to a player for each move they make
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Delay {
+    /// (Fischer) the given time is added after every move
+    Increment(Duration),
+    /// the time spent on a move is given back, up to the given time
+    Bronstein(Duration),
+    /// (simple or US delay) the clock only starts once the given time has passed on each move
+    Simple(Duration),
+}
+
+/// A number of moves (or the rest of the game) to be made in a given time
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimePeriod {
+    /// the number of moves to make in the period, or None for the rest of the game
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub delay: Option<Delay>,
+}
+
+/// The periods of a game, each of which starts once its previous period's moves have been made
+///
+/// Note: the last period repeats (e.g. "40/90" gives 90 more minutes after every 40 moves).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TimeControl(Vec<TimePeriod>);
+
+impl TimeControl {
+    /// Note: panics if `periods` is empty
+    pub fn new(periods: Vec<TimePeriod>) -> Self {
+        assert!(
+            !periods.is_empty(),
+            "A time control needs at least one period"
+        );
+        TimeControl(periods)
+    }
+
+    pub fn get_periods(&self) -> &[TimePeriod] {
+        &self.0
+    }
+
+    /// Return the time control as the value of a PGN TimeControl tag (e.g. "40/5400+30:1800")
+    ///
+    /// Note: PGN cannot record Bronstein or simple delays, so they are left out, and times are
+    /// rounded up to whole seconds (so no period is written as 0 seconds).
+    pub fn to_pgn(&self) -> String {
+        let whole_seconds = |time: Duration| time.as_secs() + u64::from(time.subsec_nanos() > 0);
+        let periods: Vec<String> = self
+            .0
+            .iter()
+            .map(|period| {
+                let moves = period
+                    .moves
+                    .map_or_else(String::new, |moves| format!("{}/", moves));
+                let increment = match period.delay {
+                    Some(Delay::Increment(increment)) => {
+                        format!("+{}", whole_seconds(increment))
+                    }
+                    _ => String::new(),
+                };
+                format!("{}{}{}", moves, whole_seconds(period.time), increment)
+            })
+            .collect();
+        periods.join(":")
+    }
+}
+
+/// Write the periods separated by commas, each as "[MOVES/]MINUTES[(+|b|d)SECONDS]", where the
+/// seconds are an increment (+), Bronstein delay (b) or simple delay (d)
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, period) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if let Some(moves) = period.moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", period.time.as_secs_f64() / 60.0)?;
+            match period.delay {
+                Some(Delay::Increment(delay)) => write!(f, "+{}", delay.as_secs_f64())?,
+                Some(Delay::Bronstein(delay)) => write!(f, "b{}", delay.as_secs_f64())?,
+                Some(Delay::Simple(delay)) => write!(f, "d{}", delay.as_secs_f64())?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The reason that a time control (such as "5+3" or "40/90+30,30+30") could not be parsed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TimeControlParseError {
+    InvalidMoves(String),
+    InvalidTime(String),
+    InvalidDelay(String),
+    /// the state of a player's clock, as written by `Clock`'s `Display`
+    InvalidClockState(String),
+}
+
+impl Display for TimeControlParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeControlParseError::InvalidMoves(moves) => {
+                write!(f, "Invalid number of moves {} (must be at least 1)", moves)
+            }
+            TimeControlParseError::InvalidTime(time) => {
+                write!(
+                    f,
+                    "Invalid time {} (must be a positive number of minutes)",
+                    time
+                )
+            }
+            TimeControlParseError::InvalidDelay(delay) => write!(
+                f,
+                "Invalid delay {} (must be a number of seconds after +, b or d)",
+                delay
+            ),
+            TimeControlParseError::InvalidClockState(state) => write!(
+                f,
+                "Invalid clock state {} (must be MILLISECONDS/PERIOD/MOVES)",
+                state
+            ),
+        }
+    }
+}
+
+impl Error for TimeControlParseError {}
+
+/// Parse periods in the format written by `Display` (e.g. "5+3", "15d5" or "40/90+30,30+30")
+impl FromStr for TimeControl {
+    type Err = TimeControlParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        fn parse_seconds(input: &str) -> Option<Duration> {
+            input
+                .parse::<f64>()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        }
+        let parse_period = |input: &str| {
+            let (moves, rest) = match input.split_once('/') {
+                Some((moves, rest)) => match moves.trim().parse::<u32>() {
+                    Ok(moves) if moves > 0 => (Some(moves), rest),
+                    _ => return Err(TimeControlParseError::InvalidMoves(String::from(moves))),
+                },
+                None => (None, input),
+            };
+            let (time, delay) = match rest.find(['+', 'b', 'd']) {
+                Some(i) => {
+                    let seconds = parse_seconds(rest[i + 1..].trim())
+                        .ok_or_else(|| TimeControlParseError::InvalidDelay(String::from(rest)))?;
+                    let delay = match &rest[i..i + 1] {
+                        "+" => Delay::Increment(seconds),
+                        "b" => Delay::Bronstein(seconds),
+                        _ => Delay::Simple(seconds),
+                    };
+                    (&rest[..i], Some(delay))
+                }
+                None => (rest, None),
+            };
+            let time = parse_seconds(time.trim())
+                .and_then(|minutes| minutes.checked_mul(60))
+                .filter(|time| !time.is_zero())
+                .ok_or_else(|| TimeControlParseError::InvalidTime(String::from(time)))?;
+            Ok(TimePeriod { moves, time, delay })
+        };
+        Ok(TimeControl(
+            input
+                .split(',')
+                .map(parse_period)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+/// The time each player has left under a time control
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    time_control: TimeControl,
+    remaining: EnumMap<Colour, Duration>,
+    /// the index of the period each player is in
+    periods: EnumMap<Colour, usize>,
+    /// the number of moves each player has made in their current period
+    moves: EnumMap<Colour, u32>,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        let time = time_control.0[0].time;
+        Clock {
+            time_control,
+            remaining: enum_map! { _ => time },
+            periods: EnumMap::default(),
+            moves: EnumMap::default(),
+        }
+    }
+
+    pub fn get_time_control(&self) -> &TimeControl {
+        &self.time_control
+    }
+
+    pub fn get_remaining(&self, player: Colour) -> Duration {
+        self.remaining[player]
+    }
+
+    /// Return the period the player is in
+    pub fn get_period(&self, player: Colour) -> &TimePeriod {
+        &self.time_control.0[self.periods[player]]
+    }
+
+    /// Return the number of moves the player has left to make before they are given more time,
+    /// or None if they have the rest of the game
+    pub fn get_moves_to_go(&self, player: Colour) -> Option<u32> {
+        self.get_period(player)
+            .moves
+            .map(|moves| moves - self.moves[player])
+    }
+
+    /// Charge the player for a move made in `elapsed`, returning false iff their time ran out
+    /// (their flag fell) before it was made
+    pub fn record_move(&mut self, player: Colour, elapsed: Duration) -> bool {
+        let period = *self.get_period(player);
+        let charged = self.get_charged(player, elapsed);
+        if charged > self.remaining[player] {
+            self.remaining[player] = Duration::ZERO;
+            return false;
+        }
+        self.remaining[player] -= charged;
+        match period.delay {
+            Some(Delay::Increment(increment)) => self.remaining[player] += increment,
+            Some(Delay::Bronstein(delay)) => self.remaining[player] += elapsed.min(delay),
+            Some(Delay::Simple(_)) | None => {}
+        }
+        self.moves[player] += 1;
+        if Some(self.moves[player]) == period.moves {
+            self.periods[player] = (self.periods[player] + 1).min(self.time_control.0.len() - 1);
+            self.moves[player] = 0;
+            let time = self.get_period(player).time;
+            self.remaining[player] += time;
+        }
+        true
+    }
+
+    /// Charge the player for `elapsed` spent on a move they have not made yet (e.g. when saving
+    /// the game part way through it)
+    pub fn charge(&mut self, player: Colour, elapsed: Duration) {
+        let charged = self.get_charged(player, elapsed);
+        self.remaining[player] = self.remaining[player].saturating_sub(charged);
+    }
+
+    /// Return true iff the player's time has run out (their flag has fallen) after `elapsed`
+    /// spent on a move they have not made yet
+    pub fn is_out_of_time(&self, player: Colour, elapsed: Duration) -> bool {
+        self.get_charged(player, elapsed) > self.remaining[player]
+    }
+
+    /// Return the time taken off the player's clock for `elapsed` spent on a move
+    fn get_charged(&self, player: Colour, elapsed: Duration) -> Duration {
+        match self.get_period(player).delay {
+            Some(Delay::Simple(delay)) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+
+    /// Take back the player's last move, after which they have made `moves_made` moves
+    ///
+    /// Note: the time spent on the move is not given back, but the time given for finishing a
+    /// period with it is taken away again.
+    pub fn undo_move(&mut self, player: Colour, moves_made: u32) {
+        let (period, moves) = self.get_period_and_moves(moves_made);
+        if Some(moves + 1) == self.time_control.0[period].moves {
+            let time = self.get_period(player).time;
+            self.remaining[player] = self.remaining[player].saturating_sub(time);
+        }
+        self.periods[player] = period;
+        self.moves[player] = moves;
+    }
+
+    /// Return the period a player is in after `moves_made` moves, and the moves made in it
+    fn get_period_and_moves(&self, moves_made: u32) -> (usize, u32) {
+        let last = self.time_control.0.len() - 1;
+        let (mut period, mut moves) = (0, moves_made);
+        while let Some(period_moves) = self.time_control.0[period].moves {
+            if moves < period_moves {
+                break;
+            }
+            moves -= period_moves;
+            if period == last {
+                moves %= period_moves;
+                break;
+            }
+            period += 1;
+        }
+        (period, moves)
+    }
+}
+
+/// Write the time control then the state of White's and Black's clocks, each as the
+/// milliseconds left, the index of their period and the moves made in it (e.g.
+/// "5+3 297000/0/2 300000/0/1"), so that the clock can be saved and restored
+impl Display for Clock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.time_control)?;
+        for &player in [Colour::White, Colour::Black].iter() {
+            write!(
+                f,
+                " {}/{}/{}",
+                self.remaining[player].as_millis(),
+                self.periods[player],
+                self.moves[player]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Clock {
+    type Err = TimeControlParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = input.split_whitespace().collect();
+        let (time_control, white, black) = match fields.as_slice() {
+            [time_control, white, black] => (time_control, white, black),
+            _ => {
+                return Err(TimeControlParseError::InvalidClockState(String::from(
+                    input,
+                )))
+            }
+        };
+        let mut clock = Clock::new(time_control.parse()?);
+        for &(player, state) in [(Colour::White, white), (Colour::Black, black)].iter() {
+            let invalid = || TimeControlParseError::InvalidClockState(String::from(*state));
+            let parts: Vec<&str> = state.split('/').collect();
+            let (remaining, period, moves): (u64, usize, u32) = match parts.as_slice() {
+                [remaining, period, moves] => (
+                    remaining.parse().map_err(|_| invalid())?,
+                    period.parse().map_err(|_| invalid())?,
+                    moves.parse().map_err(|_| invalid())?,
+                ),
+                _ => return Err(invalid()),
+            };
+            let period_moves = clock.time_control.0.get(period).ok_or_else(invalid)?.moves;
+            if period_moves.is_some_and(|period_moves| moves >= period_moves) {
+                return Err(invalid());
+            }
+            clock.remaining[player] = Duration::from_millis(remaining);
+            clock.periods[player] = period;
+            clock.moves[player] = moves;
+        }
+        Ok(clock)
+    }
+}
+
+/// Format a time on a clock (e.g. "1:05:00", "4:59" or "0:09.5" when under 20 seconds)
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if seconds >= 20 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_time, Clock, Delay, TimeControl, TimeControlParseError, TimePeriod};
+    use crate::board::piece::Colour::*;
+    use std::time::Duration;
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let control: TimeControl = "40/90+30,30+30".parse().unwrap();
+        assert_eq!(
+            control.get_periods(),
+            [
+                TimePeriod {
+                    moves: Some(40),
+                    time: secs(5400),
+                    delay: Some(Delay::Increment(secs(30)))
+                },
+                TimePeriod {
+                    moves: None,
+                    time: secs(1800),
+                    delay: Some(Delay::Increment(secs(30)))
+                }
+            ]
+        );
+        assert_eq!(control.to_pgn(), "40/5400+30:1800+30");
+        let control: TimeControl = "0.001+0.5".parse().unwrap();
+        assert_eq!(control.to_pgn(), "1+1");
+        for input in ["5+3", "40/90+30,30+30", "15d5", "0.5b2", "3"].iter() {
+            let control: TimeControl = input.parse().unwrap();
+            assert_eq!(control.to_string(), *input);
+        }
+        assert_eq!(
+            "0+3".parse::<TimeControl>(),
+            Err(TimeControlParseError::InvalidTime(String::from("0")))
+        );
+        assert_eq!(
+            "0/5".parse::<TimeControl>(),
+            Err(TimeControlParseError::InvalidMoves(String::from("0")))
+        );
+        assert_eq!(
+            "5+x".parse::<TimeControl>(),
+            Err(TimeControlParseError::InvalidDelay(String::from("5+x")))
+        );
+        // too long to fit in a Duration
+        assert_eq!(
+            "1e300".parse::<TimeControl>(),
+            Err(TimeControlParseError::InvalidTime(String::from("1e300")))
+        );
+        assert_eq!(
+            "1e18".parse::<TimeControl>(),
+            Err(TimeControlParseError::InvalidTime(String::from("1e18")))
+        );
+        assert_eq!(
+            "5+1e300".parse::<TimeControl>(),
+            Err(TimeControlParseError::InvalidDelay(String::from("5+1e300")))
+        );
+    }
+
+    #[test]
+    fn delays() {
+        let mut clock = Clock::new("1+2".parse().unwrap());
+        assert!(clock.record_move(White, secs(5)));
+        assert_eq!(clock.get_remaining(White), secs(57));
+        assert_eq!(clock.get_remaining(Black), secs(60));
+
+        let mut clock = Clock::new("1b2".parse().unwrap());
+        assert!(clock.record_move(White, secs(5)));
+        assert!(clock.record_move(Black, secs(1)));
+        assert_eq!(clock.get_remaining(White), secs(57));
+        assert_eq!(clock.get_remaining(Black), secs(60));
+
+        let mut clock = Clock::new("1d2".parse().unwrap());
+        assert!(clock.record_move(White, secs(5)));
+        assert!(clock.record_move(Black, secs(1)));
+        assert_eq!(clock.get_remaining(White), secs(57));
+        assert_eq!(clock.get_remaining(Black), secs(60));
+    }
+
+    #[test]
+    fn periods() {
+        let mut clock = Clock::new("2/1,1".parse().unwrap());
+        assert_eq!(clock.get_moves_to_go(White), Some(2));
+        assert!(clock.record_move(White, secs(10)));
+        assert_eq!(clock.get_moves_to_go(White), Some(1));
+        assert!(clock.record_move(White, secs(10)));
+        assert_eq!(clock.get_moves_to_go(White), None);
+        assert_eq!(clock.get_remaining(White), secs(100));
+
+        // the last period repeats
+        let mut clock = Clock::new("1/1".parse().unwrap());
+        assert!(clock.record_move(White, secs(30)));
+        assert!(clock.record_move(White, secs(30)));
+        assert_eq!(clock.get_remaining(White), secs(120));
+    }
+
+    #[test]
+    fn undo() {
+        let mut clock = Clock::new("3/1,1".parse().unwrap());
+        assert!(clock.record_move(White, secs(10)));
+        clock.undo_move(White, 0);
+        assert_eq!(clock.get_moves_to_go(White), Some(3));
+        assert_eq!(clock.get_remaining(White), secs(50));
+
+        // taking back the move that finished a period takes back its time
+        for _ in 0..3 {
+            assert!(clock.record_move(White, secs(10)));
+        }
+        assert_eq!(clock.get_moves_to_go(White), None);
+        assert_eq!(clock.get_remaining(White), secs(80));
+        clock.undo_move(White, 2);
+        assert_eq!(clock.get_moves_to_go(White), Some(1));
+        assert_eq!(clock.get_remaining(White), secs(20));
+
+        // the last period repeats
+        let mut clock = Clock::new("2/1".parse().unwrap());
+        for _ in 0..4 {
+            assert!(clock.record_move(White, secs(1)));
+        }
+        assert_eq!(clock.get_remaining(White), secs(176));
+        clock.undo_move(White, 3);
+        assert_eq!(clock.get_moves_to_go(White), Some(1));
+        assert_eq!(clock.get_remaining(White), secs(116));
+    }
+
+    #[test]
+    fn flag_fall() {
+        let clock = Clock::new("1d5".parse().unwrap());
+        assert!(!clock.is_out_of_time(White, secs(65)));
+        assert!(clock.is_out_of_time(White, secs(66)));
+
+        let mut clock = Clock::new("1+10".parse().unwrap());
+        assert!(clock.record_move(White, secs(60)));
+        assert!(!clock.record_move(White, secs(11)));
+        assert_eq!(clock.get_remaining(White), Duration::ZERO);
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let mut clock = Clock::new("2/1,1+2".parse().unwrap());
+        assert!(clock.record_move(White, Duration::from_millis(2_500)));
+        assert!(clock.record_move(White, secs(1)));
+        assert!(clock.record_move(Black, secs(3)));
+        assert_eq!(clock.to_string(), "2/1,1+2 116500/1/0 57000/0/1");
+        assert_eq!(clock.to_string().parse::<Clock>(), Ok(clock));
+        assert_eq!(
+            "2/1 60000/0/2 60000/0/0".parse::<Clock>(),
+            Err(TimeControlParseError::InvalidClockState(String::from(
+                "60000/0/2"
+            )))
+        );
+        assert_eq!(
+            "5+3 1/0/0".parse::<Clock>(),
+            Err(TimeControlParseError::InvalidClockState(String::from(
+                "5+3 1/0/0"
+            )))
+        );
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_time(secs(3900)), "1:05:00");
+        assert_eq!(format_time(secs(299)), "4:59");
+        assert_eq!(format_time(Duration::from_millis(9_550)), "0:09.5");
+    }
+}
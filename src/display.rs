@@ -1,12 +1,18 @@
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 
-use crate::board::{piece::Colour, BoardState};
+use crate::{
+    board::{outcome::GameOutcome, BoardState},
+    clock::Clock,
+};
 
 pub trait Display {
     fn get_unique_id(&self) -> u32;
-    fn display_board(&self, board_state: &BoardState);
-    fn display_checkmate(&self, winner: Colour);
+    /// Show the position, and the time each player has left if the game is timed
+    fn display_board(&self, board_state: &BoardState, clock: Option<&Clock>);
+    fn display_game_over(&self, outcome: GameOutcome);
+    /// Show a problem that does not stop the game (e.g. that it could not be saved)
+    fn display_warning(&self, warning: &str);
 }
 
 impl Hash for Box<dyn Display> {
@@ -33,15 +39,21 @@ impl Displays {
         Displays(displays.into_iter().collect())
     }
 
-    pub fn display_board(&self, board_state: &BoardState) {
+    pub fn display_board(&self, board_state: &BoardState, clock: Option<&Clock>) {
         for display in self.0.iter() {
-            display.display_board(board_state);
+            display.display_board(board_state, clock);
         }
     }
 
-    pub fn display_checkmate(&self, winner: Colour) {
+    pub fn display_game_over(&self, outcome: GameOutcome) {
         for display in self.0.iter() {
-            display.display_checkmate(winner);
+            display.display_game_over(outcome);
+        }
+    }
+
+    pub fn display_warning(&self, warning: &str) {
+        for display in self.0.iter() {
+            display.display_warning(warning);
         }
     }
 }
@@ -0,0 +1,218 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::board::{
+    coordinates::Move,
+    moves::{MoveError, MoveRecord},
+    outcome::{DrawReason, GameOutcome, WinReason},
+    piece::Colour::*,
+    BoardState,
+};
+use crate::{
+    clock::{Clock, TimeControlParseError},
+    pgn::parse_pgn,
+};
+
+/// The result of a game as recorded in PGN
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// the game is still in progress, was abandoned, or its result is unknown
+    Unfinished,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GameResult::WhiteWins => "1-0",
+                GameResult::BlackWins => "0-1",
+                GameResult::Draw => "1/2-1/2",
+                GameResult::Unfinished => "*",
+            }
+        )
+    }
+}
+
+impl From<GameOutcome> for GameResult {
+    fn from(outcome: GameOutcome) -> Self {
+        match outcome.get_winner() {
+            Some(White) => GameResult::WhiteWins,
+            Some(Black) => GameResult::BlackWins,
+            None => GameResult::Draw,
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = ();
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "1-0" => Ok(GameResult::WhiteWins),
+            "0-1" => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unfinished),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A game: its tag pairs, the position it started from and the moves made since
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    /// The tag pairs (e.g. ("White", "Morphy")), in the order they were given
+    pub tags: Vec<(String, String)>,
+    /// The position before the first move
+    pub start: BoardState,
+    /// The position after the last move, whose `moves` hold the history of the game
+    pub board_state: BoardState,
+    pub result: GameResult,
+    /// How the game ended, if it was played to the end by the rules
+    pub outcome: Option<GameOutcome>,
+    /// The time each player has left, if the game is timed
+    pub clock: Option<Clock>,
+}
+
+impl Game {
+    /// Return a game with no tags or moves from `start`
+    pub fn new(start: BoardState) -> Self {
+        Self {
+            tags: vec![],
+            board_state: start.clone(),
+            start,
+            result: GameResult::Unfinished,
+            outcome: None,
+            clock: None,
+        }
+    }
+
+    /// Return the value of the first tag with the given name, if any
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of the tag with the given name, adding it if it is not already set
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = String::from(value),
+            None => self.tags.push((String::from(name), String::from(value))),
+        }
+    }
+
+    /// Record that the game has ended with `outcome`
+    pub fn set_outcome(&mut self, outcome: GameOutcome) {
+        self.outcome = Some(outcome);
+        self.result = GameResult::from(outcome);
+        self.set_tag("Result", &self.result.to_string());
+        let termination = match outcome {
+            GameOutcome::Win {
+                reason: WinReason::Timeout,
+                ..
+            }
+            | GameOutcome::Draw(DrawReason::TimeoutVsInsufficientMaterial) => "time forfeit",
+            GameOutcome::Win {
+                reason: WinReason::Adjudication,
+                ..
+            }
+            | GameOutcome::Draw(DrawReason::Adjudication) => "adjudication",
+            _ => "normal",
+        };
+        self.set_tag("Termination", termination);
+    }
+
+    /// Make `m` in the current position, if it is legal
+    pub fn try_move(&mut self, m: Move) -> Result<(), MoveError> {
+        self.board_state.try_move(m)
+    }
+
+    /// Return the records of the moves made since the start, in order
+    pub fn get_move_records(&self) -> &[MoveRecord] {
+        self.board_state.get_move_records()
+    }
+
+    /// Write the game to `path` as PGN, with the state of its clock in a "Clock" tag, so that it
+    /// can be resumed with `load`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut game = self.clone();
+        if let Some(clock) = self.clock.as_ref() {
+            game.set_tag("Clock", &clock.to_string());
+        }
+        fs::write(path, game.to_pgn())
+    }
+
+    /// Read a game written by `save`, replaying its moves
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid_data = |error: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), error),
+            )
+        };
+        let mut game = parse_pgn(&fs::read_to_string(path)?)
+            .map_err(|error| invalid_data(error.to_string()))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| invalid_data(String::from("no game found")))?;
+        if let Some(index) = game.tags.iter().position(|(name, _)| name == "Clock") {
+            let (_, clock) = game.tags.remove(index);
+            game.clock = Some(
+                clock
+                    .parse()
+                    .map_err(|error: TimeControlParseError| invalid_data(error.to_string()))?,
+            );
+        }
+        Ok(game)
+    }
+
+    /// Return the moves made since the start, in order
+    pub fn get_moves(&self) -> Vec<Move> {
+        self.get_move_records()
+            .iter()
+            .map(|record| record.get_move(self.board_state.chess960))
+            .collect()
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(BoardState::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Game;
+    use crate::{board::BoardState, clock::Clock};
+    use std::{env, fs, str::FromStr};
+
+    #[test]
+    fn save_and_load() {
+        let path = env::temp_dir().join(format!("chess-save-test-{}.pgn", std::process::id()));
+        let start = BoardState::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let mut game = Game::new(start);
+        game.set_tag("WhitePlayerType", "cli");
+        for m in ["e2e4", "e8d7", "e1d2"].iter() {
+            game.try_move(m.parse().unwrap()).unwrap();
+        }
+        game.clock = Some(Clock::from_str("5+3 297000/0/2 300000/0/1").unwrap());
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.get_tag("Clock"), None);
+        assert_eq!(loaded.clock, game.clock);
+        assert_eq!(loaded.get_tag("WhitePlayerType"), Some("cli"));
+        assert_eq!(loaded.get_moves(), game.get_moves());
+        assert_eq!(loaded.board_state.to_fen(), game.board_state.to_fen());
+    }
+}
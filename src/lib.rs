@@ -1,33 +1,520 @@
-use std::io;
+use std::{
+    io,
+    path::Path,
+    time::{Instant, SystemTime},
+};
 
 pub mod ai;
 pub mod board;
 pub mod cli;
+pub mod clock;
 pub mod display;
+pub mod game;
 pub mod parsing;
+pub mod pgn;
+pub mod sprt;
+pub mod tournament;
 
-use board::{coordinates::Move, piece::Colour::*, BoardState};
+use board::{
+    moves::MoveSignal,
+    outcome::{DrawReason, GameOutcome, WinReason},
+    piece::Colour::*,
+    BoardState,
+};
+use clock::{Clock, TimeControl};
 use display::{Display, Displays};
+use game::{Game, GameResult};
+use pgn::pgn_date;
 
 pub trait Player {
-    fn get_move(&self, board_state: &mut BoardState) -> io::Result<Move>;
+    /// Return what the player does on their turn: usually a legal move
+    ///
+    /// Note: `clock` holds the time each player had left at the start of the turn, if timed.
+    fn get_move(
+        &self,
+        board_state: &mut BoardState,
+        clock: Option<&Clock>,
+    ) -> io::Result<MoveSignal>;
+    /// Return true iff the player accepts the draw their opponent has offered
+    fn accept_draw(&self, board_state: &mut BoardState) -> io::Result<bool>;
     fn get_display(&self) -> Box<dyn Display>;
+    /// Return the name of the player, as recorded in PGN
+    fn get_name(&self) -> String;
 }
 
-pub fn play_chess(white_player: &dyn Player, black_player: &dyn Player) -> io::Result<()> {
-    let mut board_state = BoardState::default();
+/// Play a game from the starting position until it ends, under `time_control` if given,
+/// returning the game played
+pub fn play_chess(
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    time_control: Option<TimeControl>,
+) -> io::Result<Game> {
+    play_chess_from(
+        BoardState::default(),
+        white_player,
+        black_player,
+        time_control,
+    )
+}
+
+/// Play a Chess960 game from the starting position with the given index until it ends, under
+/// `time_control` if given, returning the game played
+pub fn play_chess960(
+    index: u16,
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    time_control: Option<TimeControl>,
+) -> io::Result<Game> {
+    let board_state = BoardState::new_chess960(index)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    play_chess_from(board_state, white_player, black_player, time_control)
+}
+
+/// Play a game from `board_state` (e.g. an endgame parsed from FEN) until it ends, under
+/// `time_control` if given, returning the game played
+///
+/// Note: returns an `InvalidInput` error if the position cannot be played from (see
+/// `BoardState::validate`).
+pub fn play_chess_from(
+    board_state: BoardState,
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    time_control: Option<TimeControl>,
+) -> io::Result<Game> {
+    let game = new_game(board_state, white_player, black_player, time_control);
+    play_game(game, white_player, black_player, None)
+}
+
+/// Return a casual game between the players from `board_state`, with a clock for
+/// `time_control` if given, ready to be played with `play_game`
+pub fn new_game(
+    board_state: BoardState,
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    time_control: Option<TimeControl>,
+) -> Game {
+    let mut game = Game::new(board_state);
+    game.set_tag("Event", "Casual game");
+    game.set_tag("Date", &pgn_date(SystemTime::now()));
+    game.set_tag("White", &white_player.get_name());
+    game.set_tag("Black", &black_player.get_name());
+    if let Some(time_control) = time_control.as_ref() {
+        game.set_tag("TimeControl", &time_control.to_pgn());
+    }
+    game.clock = time_control.map(Clock::new);
+    game
+}
+
+/// Play `game` on from its current position until it ends (e.g. one loaded with `Game::load`),
+/// saving it to `autosave` after every move if given, and returning the game played
+///
+/// Note: returns an `InvalidInput` error if the game is already over, or its position cannot be
+/// played from (see `BoardState::validate`).
+pub fn play_game(
+    game: Game,
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    autosave: Option<&Path>,
+) -> io::Result<Game> {
+    play_adjudicated_game(game, white_player, black_player, autosave, &|_| None)
+}
+
+/// Play `game` as `play_game` does, ending it early with the outcome `adjudicate` returns for
+/// a position (before the player to move is asked for their move), if any
+pub(crate) fn play_adjudicated_game(
+    mut game: Game,
+    white_player: &dyn Player,
+    black_player: &dyn Player,
+    autosave: Option<&Path>,
+    adjudicate: &dyn Fn(&BoardState) -> Option<GameOutcome>,
+) -> io::Result<Game> {
+    if game.result != GameResult::Unfinished {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The game is already over ({})", game.result),
+        ));
+    }
+    game.board_state
+        .validate()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
     let displays = Displays::new(vec![white_player.get_display(), black_player.get_display()]);
-    while !board_state.is_checkmate() {
-        displays.display_board(&board_state);
-        let next_move = match board_state.get_next_player() {
-            White => white_player.get_move(&mut board_state)?,
-            Black => black_player.get_move(&mut board_state)?,
+    // the time spent on the current move is charged in the saved game, so that it is not given
+    // back on resuming, and failing to save only warns, as it should not end the game
+    let save = |game: &Game, path: &Path, turn_start: Instant| {
+        let mut saved = game.clone();
+        if let Some(clock) = saved.clock.as_mut() {
+            clock.charge(saved.board_state.get_next_player(), turn_start.elapsed());
+        }
+        if let Err(error) = saved.save(path) {
+            displays.display_warning(&format!(
+                "Could not save the game to {}: {}",
+                path.display(),
+                error
+            ));
+        }
+    };
+    let autosave = |game: &Game, turn_start: Instant| {
+        if let Some(path) = autosave {
+            save(game, path, turn_start);
+        }
+    };
+    displays.display_board(&game.board_state, game.clock.as_ref());
+    // the clock keeps running through undos, draw offers and saves, until the player moves
+    let mut turn_start = Instant::now();
+    let outcome = loop {
+        if let Some(outcome) = game
+            .board_state
+            .outcome()
+            .or_else(|| adjudicate(&game.board_state))
+        {
+            break outcome;
+        }
+        let player = game.board_state.get_next_player();
+        let (current_player, other_player) = match player {
+            White => (white_player, black_player),
+            Black => (black_player, white_player),
         };
-        board_state
-            .try_move(next_move)
-            .unwrap_or_else(|error| panic!("ERROR: Impossible move: {}", error));
+        let signal = current_player.get_move(&mut game.board_state, game.clock.as_ref())?;
+        // a player out of time loses whatever they do, even if they were not asked to move
+        let elapsed = turn_start.elapsed();
+        if let Some(clock) = game.clock.as_mut() {
+            if clock.is_out_of_time(player, elapsed) {
+                clock.charge(player, elapsed);
+                break if game.board_state.has_mating_material(!player) {
+                    GameOutcome::Win {
+                        winner: !player,
+                        reason: WinReason::Timeout,
+                    }
+                } else {
+                    GameOutcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+                };
+            }
+        }
+        match signal {
+            MoveSignal::Make(m) => {
+                if let Some(clock) = game.clock.as_mut() {
+                    assert!(
+                        clock.record_move(player, elapsed),
+                        "The flag should have been checked before the move"
+                    );
+                }
+                game.try_move(m)
+                    .unwrap_or_else(|error| panic!("ERROR: Impossible move: {}", error));
+                autosave(&game, Instant::now());
+                displays.display_board(&game.board_state, game.clock.as_ref());
+                turn_start = Instant::now();
+            }
+            // the player's own last move cannot be taken back without the other player's
+            MoveSignal::Undo if game.get_move_records().len() >= 2 => {
+                game.board_state.undo_move();
+                game.board_state.undo_move();
+                if let Some(clock) = game.clock.as_mut() {
+                    // the moves alternate, so the other player made the last of those left
+                    let plies = game.board_state.get_move_records().len() as u32;
+                    clock.undo_move(!player, plies.div_ceil(2));
+                    clock.undo_move(player, plies / 2);
+                }
+                autosave(&game, turn_start);
+                displays.display_board(&game.board_state, game.clock.as_ref());
+            }
+            MoveSignal::Undo => displays.display_warning("Nothing to undo"),
+            MoveSignal::Retire => {
+                break GameOutcome::Win {
+                    winner: !player,
+                    reason: WinReason::Resignation,
+                }
+            }
+            MoveSignal::ForceDraw => {
+                if let Some(reason) = game.board_state.get_claimable_draw() {
+                    break GameOutcome::Draw(reason);
+                }
+                displays.display_warning("No draw can be claimed");
+            }
+            MoveSignal::RequestDraw => {
+                if other_player.accept_draw(&mut game.board_state)? {
+                    break GameOutcome::Draw(DrawReason::Agreement);
+                }
+                displays.display_warning("Draw offer declined");
+            }
+            MoveSignal::Save(path) => save(&game, &path, turn_start),
+        }
+    };
+    displays.display_game_over(outcome);
+    game.set_outcome(outcome);
+    autosave(&game, turn_start);
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{new_game, play_chess, play_chess_from, play_game, Player};
+    use crate::{
+        board::{moves::MoveSignal, outcome::*, piece::Colour::*, BoardState},
+        clock::Clock,
+        display::Display,
+        game::{Game, GameResult},
+    };
+    use std::{
+        cell::RefCell,
+        env, fs, io,
+        path::PathBuf,
+        process,
+        rc::Rc,
+        sync::atomic::{AtomicU32, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    /// A display that shows only warnings, by adding them to a list
+    struct WarningDisplay {
+        id: u32,
+        warnings: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Display for WarningDisplay {
+        fn get_unique_id(&self) -> u32 {
+            self.id
+        }
+        fn display_board(&self, _: &BoardState, _: Option<&Clock>) {}
+        fn display_game_over(&self, _: GameOutcome) {}
+        fn display_warning(&self, warning: &str) {
+            self.warnings.borrow_mut().push(String::from(warning));
+        }
+    }
+
+    /// Return a path in the temporary directory that no other test process writes to
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("chess-lib-test-{}-{}", process::id(), name))
+    }
+
+    static NEXT_DISPLAY_ID: AtomicU32 = AtomicU32::new(100);
+
+    /// A player that sends the given signals in order, and answers draw offers with `accepts`
+    struct ScriptedPlayer {
+        signals: RefCell<Vec<MoveSignal>>,
+        accepts: bool,
+        thinking_time: Duration,
+        /// the warnings shown on this player's display (which no other player's shares)
+        warnings: Rc<RefCell<Vec<String>>>,
+        display_id: u32,
+    }
+
+    impl ScriptedPlayer {
+        fn new(signals: &[&str], accepts: bool) -> Self {
+            let signals = signals
+                .iter()
+                .rev()
+                .map(|&signal| match signal {
+                    "undo" => MoveSignal::Undo,
+                    "resign" => MoveSignal::Retire,
+                    "claim" => MoveSignal::ForceDraw,
+                    "offer" => MoveSignal::RequestDraw,
+                    m => match m.strip_prefix("save ") {
+                        Some(name) => MoveSignal::Save(temp_path(name)),
+                        None => MoveSignal::Make(m.parse().unwrap()),
+                    },
+                })
+                .collect();
+            ScriptedPlayer {
+                signals: RefCell::new(signals),
+                accepts,
+                thinking_time: Duration::ZERO,
+                warnings: Rc::default(),
+                display_id: NEXT_DISPLAY_ID.fetch_add(1, Ordering::Relaxed),
+            }
+        }
+
+        /// Return this player, waiting for `thinking_time` before each signal
+        fn thinking_for(self, thinking_time: Duration) -> Self {
+            ScriptedPlayer {
+                thinking_time,
+                ..self
+            }
+        }
+
+        fn get_warnings(&self) -> Vec<String> {
+            self.warnings.borrow().clone()
+        }
+    }
+
+    impl Player for ScriptedPlayer {
+        fn get_move(&self, _: &mut BoardState, _: Option<&Clock>) -> io::Result<MoveSignal> {
+            thread::sleep(self.thinking_time);
+            self.signals
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+        }
+        fn accept_draw(&self, _: &mut BoardState) -> io::Result<bool> {
+            Ok(self.accepts)
+        }
+        fn get_display(&self) -> Box<dyn Display> {
+            Box::new(WarningDisplay {
+                id: self.display_id,
+                warnings: Rc::clone(&self.warnings),
+            })
+        }
+        fn get_name(&self) -> String {
+            String::from("Script")
+        }
+    }
+
+    #[test]
+    fn undo_takes_back_a_move_by_each_player() {
+        let white = ScriptedPlayer::new(&["e2e4", "undo", "d2d4", "resign"], false);
+        let black = ScriptedPlayer::new(&["e7e5", "d7d5"], false);
+        let game = play_chess(&white, &black, None).unwrap();
+        let moves: Vec<String> = game.get_moves().iter().map(|m| m.to_string()).collect();
+        assert_eq!(moves, ["d2d4", "d7d5"]);
+        assert_eq!(
+            game.outcome,
+            Some(GameOutcome::Win {
+                winner: Black,
+                reason: WinReason::Resignation
+            })
+        );
+        assert_eq!(game.result, GameResult::BlackWins);
+
+        // the moves taken back no longer count towards the period
+        let white = ScriptedPlayer::new(&["e2e4", "undo", "resign"], false);
+        let black = ScriptedPlayer::new(&["e7e5"], false);
+        let game = play_chess(&white, &black, Some("3/1,1".parse().unwrap())).unwrap();
+        let clock = game.clock.unwrap();
+        assert_eq!(clock.get_moves_to_go(White), Some(3));
+        assert_eq!(clock.get_moves_to_go(Black), Some(3));
+    }
+
+    #[test]
+    fn draw_offers_and_claims() {
+        // the declined offer and the claim the rules do not allow are warned about, then ignored
+        let white = ScriptedPlayer::new(&["offer", "claim", "e2e4"], true);
+        let black = ScriptedPlayer::new(&["offer"], false);
+        let game = play_chess(&white, &black, None).unwrap();
+        assert_eq!(game.get_moves().len(), 1);
+        assert_eq!(game.outcome, Some(GameOutcome::Draw(DrawReason::Agreement)));
+        for player in [&white, &black].iter() {
+            assert_eq!(
+                player.get_warnings(),
+                ["Draw offer declined", "No draw can be claimed"]
+            );
+        }
+    }
+
+    #[test]
+    fn undo_without_moves_is_warned_about() {
+        let white = ScriptedPlayer::new(&["e2e4", "resign"], false);
+        let black = ScriptedPlayer::new(&["undo", "e7e5"], false);
+        let game = play_chess(&white, &black, None).unwrap();
+        assert_eq!(game.get_moves().len(), 2);
+        assert_eq!(black.get_warnings(), ["Nothing to undo"]);
+    }
+
+    #[test]
+    fn running_out_of_time_loses() {
+        let white = ScriptedPlayer::new(&["e2e4"], false).thinking_for(Duration::from_millis(100));
+        let black = ScriptedPlayer::new(&[], false);
+        let time_control = "0.001".parse().unwrap();
+        let game = play_chess(&white, &black, Some(time_control)).unwrap();
+        assert!(game.get_moves().is_empty());
+        assert_eq!(
+            game.outcome,
+            Some(GameOutcome::Win {
+                winner: Black,
+                reason: WinReason::Timeout
+            })
+        );
+        assert_eq!(game.get_tag("TimeControl"), Some("1"));
+        assert_eq!(game.get_tag("Termination"), Some("time forfeit"));
+
+        // a claim or offer after the flag has fallen is too late
+        for signal in ["claim", "offer", "undo"].iter() {
+            let white =
+                ScriptedPlayer::new(&[signal], true).thinking_for(Duration::from_millis(100));
+            let black = ScriptedPlayer::new(&[], true);
+            let game = play_chess(&white, &black, Some("0.001".parse().unwrap())).unwrap();
+            assert_eq!(
+                game.outcome,
+                Some(GameOutcome::Win {
+                    winner: Black,
+                    reason: WinReason::Timeout
+                })
+            );
+            assert_eq!(game.clock.unwrap().get_remaining(White), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn games_from_a_position() {
+        let board_state = "7k/8/6K1/8/8/8/Q7/8 w - - 0 1".parse().unwrap();
+        let white = ScriptedPlayer::new(&["a2a8"], false);
+        let black = ScriptedPlayer::new(&[], false);
+        let game = play_chess_from(board_state, &white, &black, None).unwrap();
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert!(game
+            .to_pgn()
+            .contains("[FEN \"7k/8/6K1/8/8/8/Q7/8 w - - 0 1\"]"));
+
+        let no_black_king = "8/8/6K1/8/8/8/Q7/8 w - - 0 1".parse().unwrap();
+        let error = play_chess_from(no_black_king, &white, &black, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn save_and_resume() {
+        let save_path = temp_path("save.pgn");
+        let autosave = temp_path("autosave.pgn");
+        // the game ends when White runs out of signals, after saving
+        let white = ScriptedPlayer::new(&["e2e4", "save save.pgn"], false);
+        let black = ScriptedPlayer::new(&["e7e5"], false);
+        let error = play_game(Game::default(), &white, &black, Some(&autosave)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        for path in [&save_path, &autosave].iter() {
+            let game = Game::load(path).unwrap();
+            fs::remove_file(path).unwrap();
+            assert_eq!(game.get_moves().len(), 2);
+            assert_eq!(game.result, GameResult::Unfinished);
+        }
+
+        let game = {
+            let mut game = Game::default();
+            game.try_move("e2e4".parse().unwrap()).unwrap();
+            game
+        };
+        let white = ScriptedPlayer::new(&[], false);
+        let black = ScriptedPlayer::new(&["resign"], false);
+        let game = play_game(game, &white, &black, None).unwrap();
+        assert_eq!(game.result, GameResult::WhiteWins);
+        let error = play_game(game, &white, &black, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn saving_charges_the_current_move() {
+        let save_path = temp_path("charged.pgn");
+        let white = ScriptedPlayer::new(&["save charged.pgn"], false)
+            .thinking_for(Duration::from_millis(200));
+        let black = ScriptedPlayer::new(&[], false);
+        let game = new_game(
+            BoardState::default(),
+            &white,
+            &black,
+            Some("1".parse().unwrap()),
+        );
+        let error = play_game(game, &white, &black, None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let game = Game::load(&save_path).unwrap();
+        fs::remove_file(&save_path).unwrap();
+        let clock = game.clock.unwrap();
+        assert!(clock.get_remaining(White) <= Duration::from_millis(59_800));
+        assert_eq!(clock.get_remaining(Black), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn failing_to_autosave_does_not_end_the_game() {
+        let autosave = temp_path("missing-directory").join("autosave.pgn");
+        let white = ScriptedPlayer::new(&["e2e4", "resign"], false);
+        let black = ScriptedPlayer::new(&["e7e5"], false);
+        let game = play_game(Game::default(), &white, &black, Some(&autosave)).unwrap();
+        assert_eq!(game.result, GameResult::BlackWins);
     }
-    let winner = board_state.get_other_player();
-    displays.display_checkmate(winner);
-    Ok(())
 }
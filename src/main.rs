@@ -1,21 +1,50 @@
-use std::io;
+use std::{
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use chess::{ai::AiPlayer, cli::InteractiveCliPlayer, play_chess, Player};
+use chess::{
+    ai::AiPlayer,
+    board::{chess960::NUM_CHESS960_POSITIONS, fen::Epd, BoardState},
+    cli::InteractiveCliPlayer,
+    clock::TimeControl,
+    game::Game,
+    new_game, play_game,
+    sprt::{run_match, Hypothesis, MatchSettings, Sprt},
+    tournament::{run_tournament, Crosstable, TournamentSettings},
+    Player,
+};
 
 extern crate clap;
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+/// The tags a game is saved with to record the type of each player (e.g. "cli" or "ai2")
+const WHITE_PLAYER_TYPE_TAG: &str = "WhitePlayerType";
+const BLACK_PLAYER_TYPE_TAG: &str = "BlackPlayerType";
+
+/// The file games with a cli player are saved to after every move, unless --autosave is given
+const DEFAULT_AUTOSAVE_PATH: &str = "chess-autosave.pgn";
 
 fn to_player(config_string: &str) -> Box<dyn Player> {
     match config_string {
         "cli" => Box::new(InteractiveCliPlayer::new()),
         "gui" => todo!(),
-        "ai1" | "ai2" | "ai3" => {
-            Box::new(AiPlayer::new(config_string.as_bytes()[2] as u8 - '0' as u8))
-        }
-        _ => Box::new(AiPlayer::new(3)),
+        _ => Box::new(AiPlayer::new(get_ai_depth(config_string).unwrap_or(3))),
     }
 }
 
+/// Return the depth of an AI player type (e.g. 2 for "ai2"), if it is one
+fn get_ai_depth(config_string: &str) -> Option<u8> {
+    config_string
+        .strip_prefix("ai")
+        .and_then(|depth| depth.parse().ok())
+        .filter(|&depth| depth > 0)
+}
+
 fn main() -> io::Result<()> {
     let matches = App::new("Chess")
         .version("0.1.0")
@@ -37,12 +66,706 @@ fn main() -> io::Result<()> {
                 .help("Sets the player type for the black player")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("chess960")
+                .long("chess960")
+                .value_name("INDEX=[0-959]")
+                .help("Plays Chess960 from the given starting position (random if not given)")
+                .takes_value(true)
+                .min_values(0)
+                .validator(|index| match index.parse::<u16>() {
+                    Ok(index) if index < NUM_CHESS960_POSITIONS => Ok(()),
+                    _ => Err(format!(
+                        "must be a number less than {}",
+                        NUM_CHESS960_POSITIONS
+                    )),
+                }),
+        )
+        .arg(
+            Arg::with_name("fen")
+                .long("fen")
+                .value_name("FEN")
+                .help("Plays from the given position (e.g. an endgame to practise)")
+                .takes_value(true)
+                .conflicts_with_all(&["chess960", "fen-file"]),
+        )
+        .arg(
+            Arg::with_name("fen-file")
+                .long("fen-file")
+                .value_name("FILE")
+                .help("Plays from the position in the first line of the given file that is not blank or a # comment")
+                .takes_value(true)
+                .conflicts_with("chess960"),
+        )
+        .arg(
+            Arg::with_name("tc")
+                .long("tc")
+                .value_name("TIME_CONTROL")
+                .help("Plays on a clock, e.g. 5+3 (5 minutes plus 3 seconds per move), 15d5 or 15b5 (5 second simple or Bronstein delay), or 40/90+30,30+30 (40 moves in 90 minutes, then 30 minutes for the rest)")
+                .takes_value(true)
+                .validator(|tc| tc.parse::<TimeControl>().map(|_| ()).map_err(|error| error.to_string())),
+        )
+        .arg(
+            Arg::with_name("autosave")
+                .long("autosave")
+                .value_name("FILE")
+                .help("Saves the game to the given file after every move, to resume it if it is quit (by default chess-autosave.pgn when a player uses the cli, or the file being resumed)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pgn-out")
+                .long("pgn-out")
+                .value_name("FILE")
+                .help("Appends each game played to the given PGN file")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("resume")
+                .about("Resumes a saved game with the same player types (unless --white or --black are given)")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .help("Sets the file the game was saved to")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("tournament")
+                .about("Plays a round robin between AI players, printing a crosstable with Elo differences")
+                .arg(
+                    Arg::with_name("players")
+                        .value_name("PLAYER_TYPES")
+                        .help("Sets the players, separated by commas (e.g. ai1,ai2,ai3)")
+                        .required(true)
+                        .validator(|players| {
+                            let player_types: Vec<&str> = players.split(',').collect();
+                            if player_types.len() < 2 {
+                                Err(String::from("must name at least two players"))
+                            } else if let Some(player_type) = player_types
+                                .iter()
+                                .find(|player_type| get_ai_depth(player_type).is_none())
+                            {
+                                Err(format!("{} is not an AI player type (e.g. ai2)", player_type))
+                            } else {
+                                Ok(())
+                            }
+                        }),
+                )
+                .arg(
+                    Arg::with_name("games")
+                        .short("g")
+                        .long("games")
+                        .value_name("N")
+                        .help("Sets the number of games each pair of players plays, alternating colours")
+                        .takes_value(true)
+                        .default_value("2")
+                        .validator(|games| match games.parse::<u32>() {
+                            Ok(games) if games > 0 => Ok(()),
+                            _ => Err(String::from("must be a positive integer")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .short("j")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Sets the number of games to play at once (the number of CPUs if not given)")
+                        .takes_value(true)
+                        .validator(|threads| match threads.parse::<usize>() {
+                            Ok(threads) if threads > 0 => Ok(()),
+                            _ => Err(String::from("must be a positive integer")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("random-plies")
+                        .long("random-plies")
+                        .value_name("N")
+                        .help("Sets the number of random moves each pair of games starts with")
+                        .takes_value(true)
+                        .default_value("4")
+                        .validator(|plies| {
+                            plies
+                                .parse::<u32>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("must be a non-negative integer"))
+                        }),
+                )
+                .arg(
+                    Arg::with_name("no-adjudication")
+                        .long("no-adjudication")
+                        .help("Plays every game to the end, instead of resigning lost games and drawing even ones early"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("match")
+                .about("Plays pairs of games between two AI players until a sequential probability ratio test decides whether the first is stronger")
+                .arg(
+                    Arg::with_name("players")
+                        .value_name("PLAYER_TYPE")
+                        .help("Sets the player being tested, then the player it is tested against (e.g. ai3 ai2)")
+                        .required(true)
+                        .number_of_values(2)
+                        .validator(|player_type| match get_ai_depth(&player_type) {
+                            Some(_) => Ok(()),
+                            None => Err(format!("{} is not an AI player type (e.g. ai2)", player_type)),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("elo0")
+                        .long("elo0")
+                        .value_name("ELO")
+                        .help("Sets the Elo difference of the null hypothesis (H0)")
+                        .takes_value(true)
+                        .default_value("0")
+                        .allow_hyphen_values(true)
+                        .validator(|elo| match elo.parse::<f64>() {
+                            Ok(elo) if elo.is_finite() => Ok(()),
+                            _ => Err(String::from("must be a number")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("elo1")
+                        .long("elo1")
+                        .value_name("ELO")
+                        .help("Sets the Elo difference of the alternative hypothesis (H1), which must be more than --elo0")
+                        .takes_value(true)
+                        .default_value("10")
+                        .allow_hyphen_values(true)
+                        .validator(|elo| match elo.parse::<f64>() {
+                            Ok(elo) if elo.is_finite() => Ok(()),
+                            _ => Err(String::from("must be a number")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("alpha")
+                        .long("alpha")
+                        .value_name("P")
+                        .help("Sets the chance of accepting H1 when H0 is true")
+                        .takes_value(true)
+                        .default_value("0.05")
+                        .validator(validate_probability),
+                )
+                .arg(
+                    Arg::with_name("beta")
+                        .long("beta")
+                        .value_name("P")
+                        .help("Sets the chance of accepting H0 when H1 is true")
+                        .takes_value(true)
+                        .default_value("0.05")
+                        .validator(validate_probability),
+                )
+                .arg(
+                    Arg::with_name("openings")
+                        .long("openings")
+                        .value_name("FILE")
+                        .help("Sets a file of positions (FEN or EPD, one per line) to start pairs of games from in a shuffled order, instead of random moves")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("N")
+                        .help("Sets the seed the openings are shuffled or chosen by (a random one if not given)")
+                        .takes_value(true)
+                        .validator(|seed| {
+                            seed.parse::<u64>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("must be a non-negative integer"))
+                        }),
+                )
+                .arg(
+                    Arg::with_name("max-pairs")
+                        .long("max-pairs")
+                        .value_name("N")
+                        .help("Sets the most pairs of games to play before stopping without a decision")
+                        .takes_value(true)
+                        .default_value("10000")
+                        .validator(|pairs| match pairs.parse::<usize>() {
+                            Ok(pairs) if pairs > 0 => Ok(()),
+                            _ => Err(String::from("must be a positive integer")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .short("j")
+                        .long("threads")
+                        .value_name("N")
+                        .help("Sets the number of pairs of games to play at once (the number of CPUs if not given)")
+                        .takes_value(true)
+                        .validator(|threads| match threads.parse::<usize>() {
+                            Ok(threads) if threads > 0 => Ok(()),
+                            _ => Err(String::from("must be a positive integer")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("random-plies")
+                        .long("random-plies")
+                        .value_name("N")
+                        .help("Sets the number of random moves each pair of games starts with, if --openings is not given")
+                        .takes_value(true)
+                        .default_value("8")
+                        .validator(|plies| {
+                            plies
+                                .parse::<u32>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("must be a non-negative integer"))
+                        }),
+                )
+                .arg(
+                    Arg::with_name("no-adjudication")
+                        .long("no-adjudication")
+                        .help("Plays every game to the end, instead of resigning lost games and drawing even ones early"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("perft")
+                .about("Counts the positions reachable in a number of plies, for each first move")
+                .arg(
+                    Arg::with_name("fen")
+                        .long("fen")
+                        .value_name("FEN")
+                        .help("Sets the position to start from (the initial position if not given)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .short("d")
+                        .long("depth")
+                        .value_name("N")
+                        .help("Sets the number of plies to search")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|depth| {
+                            depth
+                                .parse::<u32>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("must be a non-negative integer"))
+                        }),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("epd-test")
+                .about("Runs the AI on each position of an EPD test suite, checking its moves against the best (bm) and avoided (am) moves")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .help("Sets the EPD file to read positions from")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .short("d")
+                        .long("depth")
+                        .value_name("N")
+                        .help("Sets the depth of the AI's search")
+                        .takes_value(true)
+                        .default_value("3")
+                        .validator(|depth| match depth.parse::<u8>() {
+                            Ok(depth) if depth > 0 => Ok(()),
+                            _ => Err(String::from("must be a number from 1 to 255")),
+                        }),
+                )
+                .arg(
+                    Arg::with_name("time")
+                        .short("t")
+                        .long("time")
+                        .value_name("SECONDS")
+                        .help("Limits the time the AI may search each position for (it searches one level deeper at a time, up to its depth)")
+                        .takes_value(true)
+                        .validator(|time| match time.parse().map(Duration::try_from_secs_f64) {
+                            Ok(Ok(time)) if !time.is_zero() => Ok(()),
+                            _ => Err(String::from("must be a positive number of seconds")),
+                        }),
+                ),
+        )
         .get_matches();
-    let white_player_config = matches.value_of("white").unwrap_or("cli");
-    let black_player_config = matches.value_of("black").unwrap_or("ai2");
-    play_chess(
-        &(*to_player(white_player_config)),
-        &(*to_player(black_player_config)),
+    if let Some(perft_matches) = matches.subcommand_matches("perft") {
+        return perft(perft_matches);
+    }
+    if let Some(epd_test_matches) = matches.subcommand_matches("epd-test") {
+        return epd_test(epd_test_matches);
+    }
+    if let Some(tournament_matches) = matches.subcommand_matches("tournament") {
+        return tournament(&matches, tournament_matches);
+    }
+    if let Some(match_matches) = matches.subcommand_matches("match") {
+        return sprt_match(&matches, match_matches);
+    }
+    let resumed = match matches.subcommand_matches("resume") {
+        Some(resume_matches) => {
+            let path = resume_matches.value_of("file").unwrap();
+            Some((Game::load(Path::new(path))?, PathBuf::from(path)))
+        }
+        None => None,
+    };
+    // the player types of a resumed game are kept unless they are given again
+    let player_config = |name: &str, tag: &str, default: &str| {
+        matches
+            .value_of(name)
+            .or_else(|| resumed.as_ref().and_then(|(game, _)| game.get_tag(tag)))
+            .unwrap_or(default)
+            .to_string()
+    };
+    let white_player_config = player_config("white", WHITE_PLAYER_TYPE_TAG, "cli");
+    let black_player_config = player_config("black", BLACK_PLAYER_TYPE_TAG, "ai2");
+    let white_player = to_player(&white_player_config);
+    let black_player = to_player(&black_player_config);
+    let (mut game, autosave) = match resumed {
+        Some((mut game, path)) => {
+            game.set_tag("White", &white_player.get_name());
+            game.set_tag("Black", &black_player.get_name());
+            (game, Some(path))
+        }
+        None => {
+            let time_control = matches.value_of("tc").map(|tc| tc.parse().unwrap());
+            let board_state = read_start_position(&matches)?;
+            let game = new_game(
+                board_state,
+                &(*white_player),
+                &(*black_player),
+                time_control,
+            );
+            (game, None)
+        }
+    };
+    game.set_tag(WHITE_PLAYER_TYPE_TAG, &white_player_config);
+    game.set_tag(BLACK_PLAYER_TYPE_TAG, &black_player_config);
+    let autosave = match matches.value_of("autosave") {
+        Some(path) => Some(PathBuf::from(path)),
+        None if white_player_config == "cli" || black_player_config == "cli" => {
+            autosave.or_else(|| Some(PathBuf::from(DEFAULT_AUTOSAVE_PATH)))
+        }
+        None => autosave,
+    };
+    let game = play_game(
+        game,
+        &(*white_player),
+        &(*black_player),
+        autosave.as_deref(),
     )?;
+    if let Some(path) = matches.value_of("pgn-out") {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", game.to_pgn())?;
+    }
+    Ok(())
+}
+
+/// Return the position to start from given by --fen, --fen-file or --chess960, or the standard
+/// starting position
+fn read_start_position(matches: &ArgMatches) -> io::Result<BoardState> {
+    let fen = match (matches.value_of("fen"), matches.value_of("fen-file")) {
+        (Some(fen), _) => String::from(fen),
+        (None, Some(path)) => fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: no position found", path),
+                )
+            })?,
+        (None, None) if matches.is_present("chess960") => {
+            let index = match matches.value_of("chess960") {
+                Some(index) => index.parse().unwrap(),
+                None => random_chess960_index(),
+            };
+            return BoardState::new_chess960(index)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error));
+        }
+        (None, None) => return Ok(BoardState::default()),
+    };
+    BoardState::from_str(&fen).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+}
+
+fn validate_probability(p: String) -> Result<(), String> {
+    match p.parse::<f64>() {
+        Ok(p) if p > 0.0 && p < 0.5 => Ok(()),
+        _ => Err(String::from("must be a number between 0 and 0.5")),
+    }
+}
+
+fn random_chess960_index() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.subsec_nanos());
+    (nanos % NUM_CHESS960_POSITIONS as u32) as u16
+}
+
+fn perft(matches: &ArgMatches) -> io::Result<()> {
+    let mut board_state = match matches.value_of("fen") {
+        Some(fen) => BoardState::from_str(fen)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?,
+        None => BoardState::default(),
+    };
+    let depth = matches.value_of("depth").unwrap().parse().unwrap();
+    let start = Instant::now();
+    let divide = board_state.divide(depth);
+    for &(m, nodes) in divide.iter() {
+        println!("{}: {}", m, nodes);
+    }
+    let nodes = match depth {
+        0 => 1,
+        _ => divide.iter().map(|&(_, nodes)| nodes).sum(),
+    };
+    let elapsed = start.elapsed();
+    println!("\nNodes searched: {}", nodes);
+    println!(
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64()
+    );
+    Ok(())
+}
+
+fn epd_test(matches: &ArgMatches) -> io::Result<()> {
+    let path = matches.value_of("file").unwrap();
+    let depth = matches.value_of("depth").unwrap().parse().unwrap();
+    let mut player = AiPlayer::new(depth);
+    if let Some(time) = matches.value_of("time") {
+        player = player.with_time_limit(Duration::from_secs_f64(time.parse().unwrap()));
+    }
+    let invalid_data = |line_number: usize, error: &dyn Display| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", path, line_number, error),
+        )
+    };
+
+    let (mut passed, mut total) = (0, 0);
+    let start = Instant::now();
+    for (line_number, line) in (1..).zip(fs::read_to_string(path)?.lines()) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let epd: Epd = line
+            .parse()
+            .map_err(|error| invalid_data(line_number, &error))?;
+        let best_moves = epd
+            .best_moves()
+            .map_err(|error| invalid_data(line_number, &error))?;
+        let avoid_moves = epd
+            .avoid_moves()
+            .map_err(|error| invalid_data(line_number, &error))?;
+        if best_moves.is_empty() && avoid_moves.is_empty() {
+            continue;
+        }
+
+        let mut board_state = epd.board_state.clone();
+        let m = player.choose_move(&mut board_state);
+        let san = board_state
+            .to_san(m)
+            .map_err(|error| invalid_data(line_number, &error))?;
+        let is_pass =
+            (best_moves.is_empty() || best_moves.contains(&m)) && !avoid_moves.contains(&m);
+        total += 1;
+        if is_pass {
+            passed += 1;
+        }
+        let expected: Vec<String> = ["bm", "am"]
+            .iter()
+            .filter_map(|&opcode| {
+                epd.get_operands(opcode)
+                    .map(|operands| format!("{} {}", opcode, operands.join(" ")))
+            })
+            .collect();
+        println!(
+            "{} {}: {} ({})",
+            if is_pass { "PASS" } else { "FAIL" },
+            epd.id()
+                .map_or_else(|| format!("line {}", line_number), String::from),
+            san,
+            expected.join("; ")
+        );
+    }
+    println!(
+        "\nPassed {}/{} in {:.3}s",
+        passed,
+        total,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn tournament(matches: &ArgMatches, tournament_matches: &ArgMatches) -> io::Result<()> {
+    let specs: Vec<String> = tournament_matches
+        .value_of("players")
+        .unwrap()
+        .split(',')
+        .map(String::from)
+        .collect();
+    let mut settings = TournamentSettings {
+        games_per_pairing: tournament_matches
+            .value_of("games")
+            .unwrap()
+            .parse()
+            .unwrap(),
+        random_plies: tournament_matches
+            .value_of("random-plies")
+            .unwrap()
+            .parse()
+            .unwrap(),
+        time_control: matches.value_of("tc").map(|tc| tc.parse().unwrap()),
+        ..TournamentSettings::default()
+    };
+    if let Some(threads) = tournament_matches.value_of("threads") {
+        settings.threads = threads.parse().unwrap();
+    }
+    if tournament_matches.is_present("no-adjudication") {
+        settings.adjudication = None;
+    }
+
+    let num_games = specs.len() * (specs.len() - 1) / 2 * settings.games_per_pairing as usize;
+    let mut num_finished = 0;
+    let start = Instant::now();
+    let games = run_tournament(&specs, &to_player, &settings, &mut |game| {
+        num_finished += 1;
+        println!(
+            "[{}/{}] {} - {}: {} ({})",
+            num_finished,
+            num_games,
+            specs[game.white],
+            specs[game.black],
+            game.game.result,
+            game.game
+                .outcome
+                .map_or_else(|| String::from("unfinished"), |outcome| outcome.to_string())
+        );
+    })?;
+    println!(
+        "\n{}\nPlayed {} games in {:.1}s",
+        Crosstable::new(specs, &games),
+        games.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if let Some(path) = matches.value_of("pgn-out") {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        for game in games.iter() {
+            writeln!(file, "{}", game.game.to_pgn())?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the positions (as FEN or EPD) on each line of a file, skipping blank lines and comments
+fn read_openings(path: &str) -> io::Result<Vec<BoardState>> {
+    let mut openings = vec![];
+    for (line_number, line) in (1..).zip(fs::read_to_string(path)?.lines()) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let board_state = match BoardState::from_str(line) {
+            Ok(board_state) => board_state,
+            Err(error) => line
+                .parse::<Epd>()
+                .map(|epd| epd.board_state)
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}:{}: {}", path, line_number, error),
+                    )
+                })?,
+        };
+        openings.push(board_state);
+    }
+    if openings.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: no positions found", path),
+        ));
+    }
+    Ok(openings)
+}
+
+fn sprt_match(matches: &ArgMatches, match_matches: &ArgMatches) -> io::Result<()> {
+    let specs: Vec<&str> = match_matches.values_of("players").unwrap().collect();
+    let parse = |name| match_matches.value_of(name).unwrap().parse().unwrap();
+    let sprt = Sprt {
+        elo0: parse("elo0"),
+        elo1: parse("elo1"),
+        alpha: parse("alpha"),
+        beta: parse("beta"),
+    };
+    if sprt.elo1 <= sprt.elo0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--elo1 must be more than --elo0",
+        ));
+    }
+    let seed = match match_matches.value_of("seed") {
+        Some(seed) => seed.parse().unwrap(),
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64),
+    };
+    let mut settings = MatchSettings {
+        sprt,
+        max_pairs: match_matches
+            .value_of("max-pairs")
+            .unwrap()
+            .parse()
+            .unwrap(),
+        random_plies: match_matches
+            .value_of("random-plies")
+            .unwrap()
+            .parse()
+            .unwrap(),
+        seed,
+        time_control: matches.value_of("tc").map(|tc| tc.parse().unwrap()),
+        ..MatchSettings::default()
+    };
+    if let Some(path) = match_matches.value_of("openings") {
+        settings.openings = read_openings(path)?;
+    }
+    if let Some(threads) = match_matches.value_of("threads") {
+        settings.threads = threads.parse().unwrap();
+    }
+    if match_matches.is_present("no-adjudication") {
+        settings.adjudication = None;
+    }
+    let mut pgn_out = match matches.value_of("pgn-out") {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        None => None,
+    };
+
+    println!("{} vs {} (seed {})", specs[0], specs[1], seed);
+    let start = Instant::now();
+    let mut write_error = None;
+    let status = run_match(
+        [specs[0], specs[1]],
+        &to_player,
+        &settings,
+        &mut |status, games| {
+            println!("{}", status);
+            if let Some(file) = pgn_out.as_mut() {
+                for game in games.iter() {
+                    if let Err(error) = writeln!(file, "{}", game.to_pgn()) {
+                        write_error.get_or_insert(error);
+                    }
+                }
+            }
+        },
+    )?;
+    if let Some(error) = write_error {
+        return Err(error);
+    }
+    println!(
+        "\n{} in {:.1}s",
+        match status.decision {
+            Some(Hypothesis::H0) => format!(
+                "H0 accepted: {} is not {} Elo stronger than {}",
+                specs[0], sprt.elo1, specs[1]
+            ),
+            Some(Hypothesis::H1) => format!(
+                "H1 accepted: {} is more than {} Elo stronger than {}",
+                specs[0], sprt.elo0, specs[1]
+            ),
+            None => String::from("No decision"),
+        },
+        start.elapsed().as_secs_f64()
+    );
     Ok(())
 }
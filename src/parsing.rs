@@ -1,51 +1,104 @@
-use crate::board::coordinates::{
-    ColumnIndex::{self, *},
-    Coordinate,
-    RowIndex::*,
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
 };
 
-pub fn parse_coordinate(input: &str) -> Result<Coordinate, String> {
-    if input.len() != 2 {
-        return Err(String::from("Coordinates must have exactly two characters"));
-    };
-    let input_bytes = input.as_bytes();
-    let parse_row = |column: ColumnIndex| -> Result<Coordinate, String> {
-        match input_bytes[1] {
-            b'1' => Ok(Coordinate { row: _1, column }),
-            b'2' => Ok(Coordinate { row: _2, column }),
-            b'3' => Ok(Coordinate { row: _3, column }),
-            b'4' => Ok(Coordinate { row: _4, column }),
-            b'5' => Ok(Coordinate { row: _5, column }),
-            b'6' => Ok(Coordinate { row: _6, column }),
-            b'7' => Ok(Coordinate { row: _7, column }),
-            b'8' => Ok(Coordinate { row: _8, column }),
-            _ => Err(String::from(format!(
-                "Invalid row {}",
-                input_bytes[1] as char
-            ))),
+use crate::board::{
+    coordinates::{
+        ColumnIndex::{self, *},
+        RowIndex::{self, *},
+    },
+    piece::PieceType::{self, *},
+};
+
+/// The reason that a coordinate (such as "e4") could not be parsed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CoordinateParseError {
+    WrongLength,
+    InvalidRow(char),
+    InvalidColumn(char),
+}
+
+impl Display for CoordinateParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordinateParseError::WrongLength => {
+                write!(f, "Coordinates must have exactly two characters")
+            }
+            CoordinateParseError::InvalidRow(c) => write!(f, "Invalid row {}", c),
+            CoordinateParseError::InvalidColumn(c) => {
+                write!(f, "Invalid column {} (must be a-h or A-H)", c)
+            }
+        }
+    }
+}
+
+impl Error for CoordinateParseError {}
+
+/// The input could not be parsed as a type of piece to promote a Pawn to
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PromotionTypeParseError(pub String);
+
+impl Display for PromotionTypeParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid promotion {} (must be one of Q, R, B or N)",
+            self.0
+        )
+    }
+}
+
+impl Error for PromotionTypeParseError {}
+
+/// The reason that a move in long algebraic notation (such as "e7e8q") could not be parsed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MoveParseError {
+    WrongLength,
+    InvalidCoordinate(CoordinateParseError),
+    InvalidPromotion(PromotionTypeParseError),
+}
+
+impl Display for MoveParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveParseError::WrongLength => write!(
+                f,
+                "Moves must have four characters, or five with a promotion (e.g. e2e4 or e7e8q)"
+            ),
+            MoveParseError::InvalidCoordinate(error) => write!(f, "{}", error),
+            MoveParseError::InvalidPromotion(error) => write!(f, "{}", error),
         }
-    };
+    }
+}
+
+impl Error for MoveParseError {}
 
-    // TODO: use ColumnIndex::parse()
+impl From<CoordinateParseError> for MoveParseError {
+    fn from(error: CoordinateParseError) -> Self {
+        MoveParseError::InvalidCoordinate(error)
+    }
+}
 
-    match input_bytes[0] {
-        b'a' | b'A' => parse_row(A),
-        b'b' | b'B' => parse_row(B),
-        b'c' | b'C' => parse_row(C),
-        b'd' | b'D' => parse_row(D),
-        b'e' | b'E' => parse_row(E),
-        b'f' | b'F' => parse_row(F),
-        b'g' | b'G' => parse_row(G),
-        b'h' | b'H' => parse_row(H),
-        _ => Err(String::from(format!(
-            "Invalid column {}",
-            input_bytes[0] as char
-        ))),
+impl From<PromotionTypeParseError> for MoveParseError {
+    fn from(error: PromotionTypeParseError) -> Self {
+        MoveParseError::InvalidPromotion(error)
+    }
+}
+
+/// Parse the type of piece to promote a Pawn to (Q, R, B or N, in either case)
+pub fn parse_promotion_type(input: &str) -> Result<PieceType, PromotionTypeParseError> {
+    match input {
+        "q" | "Q" => Ok(Queen),
+        "r" | "R" => Ok(Rook),
+        "b" | "B" => Ok(Bishop),
+        "n" | "N" => Ok(Knight),
+        _ => Err(PromotionTypeParseError(String::from(input))),
     }
 }
 
 impl ColumnIndex {
-    pub fn parse(c: char) -> Result<Self, String> {
+    pub fn parse(c: char) -> Result<Self, CoordinateParseError> {
         let result = match c {
             'a' | 'A' => A,
             'b' | 'B' => B,
@@ -55,7 +108,24 @@ impl ColumnIndex {
             'f' | 'F' => F,
             'g' | 'G' => G,
             'h' | 'H' => H,
-            _ => return Err(format!("Invalid Column: {} (must be a-h or A-H)", c)),
+            _ => return Err(CoordinateParseError::InvalidColumn(c)),
+        };
+        Ok(result)
+    }
+}
+
+impl RowIndex {
+    pub fn parse(c: char) -> Result<Self, CoordinateParseError> {
+        let result = match c {
+            '1' => _1,
+            '2' => _2,
+            '3' => _3,
+            '4' => _4,
+            '5' => _5,
+            '6' => _6,
+            '7' => _7,
+            '8' => _8,
+            _ => return Err(CoordinateParseError::InvalidRow(c)),
         };
         Ok(result)
     }
@@ -0,0 +1,581 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    iter::Peekable,
+    str::{Chars, FromStr},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    board::{fen::FenError, piece::Colour::*, san::SanError, BoardState},
+    game::{Game, GameResult},
+};
+
+/// The reason that a game in a PGN database could not be read
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PgnErrorKind {
+    /// a tag pair that is not of the form [Name "value"]
+    InvalidTag(String),
+    /// a comment, tag or variation that is not closed before the end of the input
+    Unterminated(&'static str),
+    /// a ')' that does not close a variation
+    UnexpectedToken(String),
+    InvalidFen(FenError),
+    /// the move at the given ply (counted from 1 at the start of the game) is not legal
+    IllegalMove {
+        ply: usize,
+        error: SanError,
+    },
+}
+
+/// An error in the game with the given index (counted from 1) of a PGN database
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PgnError {
+    pub game: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "PGN PARSE ERROR: game {}", self.game)?;
+        match &self.kind {
+            PgnErrorKind::InvalidTag(tag) => write!(f, ": invalid tag pair [{}]", tag),
+            PgnErrorKind::Unterminated(what) => write!(f, ": unterminated {}", what),
+            PgnErrorKind::UnexpectedToken(token) => write!(f, ": unexpected {}", token),
+            PgnErrorKind::InvalidFen(error) => write!(f, ": {}", error),
+            PgnErrorKind::IllegalMove { ply, error } => write!(f, ", ply {}: {}", ply, error),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    /// a move in SAN, without any move number
+    Move(String),
+    Result(GameResult),
+    StartVariation,
+    EndVariation,
+}
+
+/// Splits PGN into tokens, dropping comments, escaped lines, NAGs and move numbers
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    at_line_start: bool,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(pgn: &'a str) -> Self {
+        Self {
+            chars: pgn.chars().peekable(),
+            at_line_start: true,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        self.at_line_start = c == Some('\n');
+        c
+    }
+
+    /// Return the characters up to (but not including) the first one matching `end`
+    fn take_until(&mut self, end: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if end(c) {
+                break;
+            }
+            taken.push(c);
+            self.next_char();
+        }
+        taken
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnErrorKind> {
+        loop {
+            let at_line_start = self.at_line_start;
+            let c = match self.next_char() {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            match c {
+                _ if c.is_whitespace() => {}
+                // a line escaped from PGN processing
+                '%' if at_line_start => {
+                    self.take_until(|c| c == '\n');
+                }
+                ';' => {
+                    self.take_until(|c| c == '\n');
+                }
+                '{' => {
+                    self.take_until(|c| c == '}');
+                    if self.next_char().is_none() {
+                        return Err(PgnErrorKind::Unterminated("comment"));
+                    }
+                }
+                '$' => {
+                    self.take_until(|c| !c.is_ascii_digit());
+                }
+                '[' => return self.read_tag().map(Some),
+                '(' => return Ok(Some(Token::StartVariation)),
+                ')' => return Ok(Some(Token::EndVariation)),
+                _ => {
+                    let mut symbol = c.to_string();
+                    symbol.push_str(
+                        &self.take_until(|c| c.is_whitespace() || "[]{}();$".contains(c)),
+                    );
+                    if let Ok(result) = GameResult::from_str(&symbol) {
+                        return Ok(Some(Token::Result(result)));
+                    }
+                    // drop any move number, e.g. "12." or "12..."
+                    let digits = symbol.chars().take_while(char::is_ascii_digit).count();
+                    let san = match symbol[digits..].strip_prefix('.') {
+                        Some(rest) if digits > 0 => rest.trim_start_matches('.'),
+                        _ => &symbol,
+                    };
+                    if !san.is_empty() {
+                        return Ok(Some(Token::Move(String::from(san))));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read a tag pair, after its opening '['
+    fn read_tag(&mut self) -> Result<Token, PgnErrorKind> {
+        let name = self.take_until(|c| c.is_whitespace() || c == '"' || c == ']');
+        self.take_until(|c| !c.is_whitespace());
+        let mut value = String::new();
+        let quoted = self.chars.peek() == Some(&'"');
+        if quoted {
+            self.next_char();
+            loop {
+                match self.next_char() {
+                    Some('\\') => match self.next_char() {
+                        Some(c) => value.push(c),
+                        None => return Err(PgnErrorKind::Unterminated("tag")),
+                    },
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return Err(PgnErrorKind::Unterminated("tag")),
+                }
+            }
+            self.take_until(|c| !c.is_whitespace());
+        }
+        let rest = self.take_until(|c| c == ']');
+        if self.next_char().is_none() {
+            return Err(PgnErrorKind::Unterminated("tag"));
+        }
+        if !quoted || name.is_empty() || !rest.is_empty() {
+            return Err(PgnErrorKind::InvalidTag(format!(
+                "{} {}{}",
+                name, value, rest
+            )));
+        }
+        Ok(Token::Tag(name, value))
+    }
+}
+
+/// Return the game with `tags`, starting from the position in its FEN tag if it has one
+fn start_game(tags: Vec<(String, String)>) -> Result<Game, PgnErrorKind> {
+    let start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => BoardState::from_str(fen).map_err(PgnErrorKind::InvalidFen)?,
+        None => BoardState::default(),
+    };
+    let mut game = Game::new(start);
+    game.tags = tags;
+    Ok(game)
+}
+
+/// Read every game in a PGN database, replaying the moves of each
+///
+/// Only the main line of each game is kept: comments, NAGs and variations are skipped.
+///
+/// Examples:
+/// ```
+/// use chess::{game::GameResult, pgn::parse_pgn};
+/// let games = parse_pgn(
+///     r#"[Event "Fool's mate"]
+///
+/// 1. f3 e5 2. g4 {a blunder} Qh4# 0-1"#,
+/// )
+/// .unwrap();
+/// assert_eq!(games.len(), 1);
+/// assert_eq!(games[0].get_tag("Event"), Some("Fool's mate"));
+/// assert_eq!(games[0].get_moves().len(), 4);
+/// assert_eq!(games[0].result, GameResult::BlackWins);
+/// ```
+pub fn parse_pgn(pgn: &str) -> Result<Vec<Game>, PgnError> {
+    let mut lexer = Lexer::new(pgn);
+    let mut games = vec![];
+    let mut tags = vec![];
+    let mut game: Option<Game> = None;
+    let mut variation_depth = 0;
+    let error = |games: &Vec<Game>, kind| PgnError {
+        game: games.len() + 1,
+        kind,
+    };
+    while let Some(token) = lexer.next_token().map_err(|kind| error(&games, kind))? {
+        match token {
+            _ if variation_depth > 0 => match token {
+                Token::StartVariation => variation_depth += 1,
+                Token::EndVariation => variation_depth -= 1,
+                _ => {}
+            },
+            Token::StartVariation => variation_depth += 1,
+            Token::EndVariation => {
+                return Err(error(
+                    &games,
+                    PgnErrorKind::UnexpectedToken(String::from(")")),
+                ))
+            }
+            Token::Tag(name, value) => {
+                // tags after movetext without a result begin the next game
+                if let Some(game) = game.take() {
+                    games.push(game);
+                }
+                tags.push((name, value));
+            }
+            Token::Move(san) => {
+                if game.is_none() {
+                    game = Some(
+                        start_game(std::mem::take(&mut tags))
+                            .map_err(|kind| error(&games, kind))?,
+                    );
+                }
+                let game = game.as_mut().expect("The game should have been started");
+                let ply = game.get_move_records().len() + 1;
+                let illegal_move = |error| PgnErrorKind::IllegalMove { ply, error };
+                let m = game
+                    .board_state
+                    .from_san(&san)
+                    .map_err(|san_error| error(&games, illegal_move(san_error)))?;
+                game.try_move(m).map_err(|_| {
+                    error(&games, illegal_move(SanError::NoMatchingMove(san.clone())))
+                })?;
+            }
+            Token::Result(result) => {
+                let mut game = match game.take() {
+                    Some(game) => game,
+                    None => {
+                        start_game(std::mem::take(&mut tags)).map_err(|kind| error(&games, kind))?
+                    }
+                };
+                game.result = result;
+                games.push(game);
+            }
+        }
+    }
+    if variation_depth > 0 {
+        return Err(error(&games, PgnErrorKind::Unterminated("variation")));
+    }
+    if let Some(game) = game {
+        games.push(game);
+    } else if !tags.is_empty() {
+        games.push(start_game(tags).map_err(|kind| error(&games, kind))?);
+    }
+    Ok(games)
+}
+
+/// The tags that every exported game has, in the order they are written
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// The longest line of exported movetext
+const MAX_LINE_LENGTH: usize = 79;
+
+/// Formats a Game as PGN: the Seven Tag Roster (with "?" for any that are unknown) and its other
+/// tags, then its moves in SAN, how it ended and its result
+pub struct Pgn<'a>(pub &'a Game);
+
+impl Game {
+    /// Return the PGN of this game
+    ///
+    /// Examples:
+    /// ```
+    /// use chess::{board::outcome::*, game::Game, pgn::parse_pgn};
+    /// let mut game = Game::default();
+    /// for &san in ["f3", "e5", "g4", "Qh4#"].iter() {
+    ///     let m = game.board_state.from_san(san).unwrap();
+    ///     game.try_move(m).unwrap();
+    /// }
+    /// let outcome = game.board_state.outcome().unwrap();
+    /// game.set_outcome(outcome);
+    /// let pgn = game.to_pgn();
+    /// assert!(pgn.starts_with("[Event \"?\"]\n"));
+    /// assert!(pgn.ends_with("1. f3 e5 2. g4 Qh4# {Black wins by checkmate} 0-1\n"));
+    /// assert_eq!(parse_pgn(&pgn).unwrap()[0].get_moves(), game.get_moves());
+    /// ```
+    pub fn to_pgn(&self) -> String {
+        Pgn(self).to_string()
+    }
+}
+
+impl Display for Pgn<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let game = self.0;
+        let write_tag = |f: &mut Formatter<'_>, name: &str, value: &str| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)
+        };
+        for &name in SEVEN_TAG_ROSTER.iter() {
+            match name {
+                "Result" => write_tag(f, name, &game.result.to_string())?,
+                _ => write_tag(f, name, game.get_tag(name).unwrap_or("?"))?,
+            }
+        }
+        let start_fen = game.start.to_fen();
+        let is_standard_start = start_fen == BoardState::default().to_fen();
+        for (name, value) in game.tags.iter() {
+            let is_written = SEVEN_TAG_ROSTER.contains(&name.as_str())
+                || (!is_standard_start && (name == "SetUp" || name == "FEN"));
+            if !is_written {
+                write_tag(f, name, value)?;
+            }
+        }
+        if !is_standard_start {
+            write_tag(f, "SetUp", "1")?;
+            write_tag(f, "FEN", &start_fen)?;
+        }
+        writeln!(f)?;
+
+        // the movetext, as words to be wrapped into lines
+        let mut words = vec![];
+        let mut board_state = game.start.clone();
+        for (ply, record) in game.get_move_records().iter().enumerate() {
+            // keep each move number on the same line as its move
+            let number = board_state.get_fullmove_number();
+            let number = match board_state.get_next_player() {
+                White => format!("{}. ", number),
+                Black if ply == 0 => format!("{}... ", number),
+                Black => String::new(),
+            };
+            let m = record.get_move(board_state.chess960);
+            let san = board_state.to_san(m).map_err(|_| fmt::Error)?;
+            words.push(number + &san);
+            board_state.try_move(m).map_err(|_| fmt::Error)?;
+        }
+        if let Some(outcome) = game.outcome {
+            words.push(format!("{{{}}}", outcome));
+        }
+        words.push(game.result.to_string());
+
+        let mut line_length = 0;
+        for word in words {
+            if line_length > 0 && line_length + 1 + word.len() > MAX_LINE_LENGTH {
+                writeln!(f)?;
+                line_length = 0;
+            } else if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{}", word)?;
+            line_length += word.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Return the date of `time` as written in PGN (e.g. "2021.07.31"), in UTC
+pub fn pgn_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() / 86400) as i64;
+    // convert days since 1970-01-01 to a civil date (Howard Hinnant's days_from_civil, reversed)
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_pgn, pgn_date, PgnError, PgnErrorKind};
+    use crate::{
+        board::{fen::FenError, outcome::*, san::SanError},
+        game::GameResult,
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const OPERA_GAME: &str = r#"[Event "A Night at the Opera"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[White "Morphy, Paul"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1.e4 e5 2.Nf3 d6 3.d4 Bg4 {This is a weak move already.} 4.dxe5 Bxf3 5.Qxf3 dxe5
+6.Bc4 Nf6 7.Qb3 Qe7 8.Nc3 c6 9.Bg5 {Black is in what's like a zugzwang position
+here.} b5 $6 (9...Qb4 10.Qxb4 (10.Bxf6 gxf6) 10...Bxb4) 10.Nxb5! cxb5 11.Bxb5+ Nbd7
+12.O-O-O Rd8 13.Rxd7 Rxd7 14.Rd1 Qe6 ; the only move
+15.Bxd7+ Nxd7 16.Qb8+! Nxb8 17.Rd8# 1-0
+"#;
+
+    #[test]
+    fn replays_a_game_with_comments_nags_and_variations() {
+        let mut games = parse_pgn(OPERA_GAME).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &mut games[0];
+        assert_eq!(game.tags.len(), 6);
+        assert_eq!(game.get_tag("White"), Some("Morphy, Paul"));
+        assert_eq!(game.get_tag("Black"), Some("Duke Karl / Count Isouard"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.get_moves().len(), 33);
+        assert!(game.board_state.is_checkmate());
+        assert_eq!(game.start, Default::default());
+    }
+
+    #[test]
+    fn multiple_games_and_start_positions() {
+        let pgn = format!(
+            r#"{}
+[Event "Escaped"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]
+% this line is not part of the game
+1. O-O Kd7 *
+
+[Event "No result"]
+[Annotator "\"Q\" \\ A"]
+1. d4 d5 2. c4
+[Event "Tags only"]
+"#,
+            OPERA_GAME
+        );
+        let games = parse_pgn(&pgn).unwrap();
+        assert_eq!(games.len(), 4);
+        assert_eq!(games[1].start.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(
+            games[1].board_state.to_fen(),
+            "8/3k4/8/8/8/8/8/5RK1 w - - 2 2"
+        );
+        assert_eq!(games[1].result, GameResult::Unfinished);
+        assert_eq!(games[2].get_tag("Annotator"), Some("\"Q\" \\ A"));
+        assert_eq!(games[2].get_moves().len(), 3);
+        assert_eq!(games[2].result, GameResult::Unfinished);
+        assert_eq!(games[3].get_tag("Event"), Some("Tags only"));
+        assert!(games[3].get_moves().is_empty());
+    }
+
+    #[test]
+    fn errors_give_the_game_and_ply() {
+        let pgn = format!("{}\n[Event \"Second\"]\n1. e4 e5 2. Ke3 *", OPERA_GAME);
+        let error = parse_pgn(&pgn).unwrap_err();
+        assert_eq!(
+            error,
+            PgnError {
+                game: 2,
+                kind: PgnErrorKind::IllegalMove {
+                    ply: 3,
+                    error: SanError::NoMatchingMove(String::from("Ke3"))
+                }
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "PGN PARSE ERROR: game 2, ply 3: Illegal move Ke3"
+        );
+        assert_eq!(
+            parse_pgn("[FEN \"8/8/8 w - - 0 1\"]\n*").unwrap_err(),
+            PgnError {
+                game: 1,
+                kind: PgnErrorKind::InvalidFen(FenError::WrongNumberOfRows(3))
+            }
+        );
+        assert_eq!(
+            parse_pgn("1. e4 {unclosed").unwrap_err().kind,
+            PgnErrorKind::Unterminated("comment")
+        );
+        assert_eq!(
+            parse_pgn("1. e4 (1. d4 *").unwrap_err().kind,
+            PgnErrorKind::Unterminated("variation")
+        );
+        assert_eq!(
+            parse_pgn("[Event Unquoted]").unwrap_err().kind,
+            PgnErrorKind::InvalidTag(String::from("Event Unquoted"))
+        );
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let games = parse_pgn(OPERA_GAME).unwrap();
+        let pgn = games[0].to_pgn();
+        assert!(pgn.starts_with(
+            r#"[Event "A Night at the Opera"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Morphy, Paul"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7
+8. Nc3 c6"#
+        ));
+        assert!(pgn.ends_with("16. Qb8+ Nxb8 17. Rd8# 1-0\n"));
+        assert!(pgn.lines().all(|line| line.len() <= 79));
+        let reparsed = parse_pgn(&pgn).unwrap();
+        assert_eq!(reparsed[0].get_moves(), games[0].get_moves());
+        assert_eq!(reparsed[0].result, GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn export_from_a_position_with_black_to_move() {
+        let mut games = parse_pgn(
+            r#"[White "\"Q\" \\ A"]
+[FEN "7k/4Q3/6K1/8/8/8/8/8 b - - 0 60"]
+60... Kg8 {forced} 61. Qe8#"#,
+        )
+        .unwrap();
+        let outcome = games[0].board_state.outcome().unwrap();
+        games[0].set_outcome(outcome);
+        assert_eq!(
+            games[0].to_pgn(),
+            r#"[Event "?"]
+[Site "?"]
+[Date "?"]
+[Round "?"]
+[White "\"Q\" \\ A"]
+[Black "?"]
+[Result "1-0"]
+[Termination "normal"]
+[SetUp "1"]
+[FEN "7k/4Q3/6K1/8/8/8/8/8 b - - 0 60"]
+
+60... Kg8 61. Qe8# {White wins by checkmate} 1-0
+"#
+        );
+        assert_eq!(
+            outcome,
+            GameOutcome::Win {
+                winner: crate::board::piece::Colour::White,
+                reason: WinReason::Checkmate
+            }
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(pgn_date(UNIX_EPOCH), "1970.01.01");
+        assert_eq!(
+            pgn_date(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000.02.29"
+        );
+        assert_eq!(
+            pgn_date(UNIX_EPOCH + Duration::from_secs(1_627_689_600)),
+            "2021.07.31"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io, thread,
+};
+
+use crate::{
+    board::BoardState,
+    clock::TimeControl,
+    game::{Game, GameResult},
+    tournament::{play_tournament_game, random_opening, run_in_parallel, Adjudication, Score},
+    Player,
+};
+
+/// The hypotheses of a sequential probability ratio test: that the first player is `elo0`
+/// stronger than the second (H0), or `elo1` stronger (H1), with the chances `alpha` of
+/// accepting H1 when H0 is true and `beta` of accepting H0 when H1 is true
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// Return the log-likelihood ratios below which H0 is accepted, and above which H1 is
+    pub fn get_bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Return the hypothesis accepted with the log-likelihood ratio `llr`, if any
+    pub fn get_decision(&self, llr: f64) -> Option<Hypothesis> {
+        let (lower, upper) = self.get_bounds();
+        if llr <= lower {
+            Some(Hypothesis::H0)
+        } else if llr >= upper {
+            Some(Hypothesis::H1)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Hypothesis {
+    H0,
+    H1,
+}
+
+/// Return the expected score (from 0 to 1) of a player `elo` stronger than their opponent
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The number of pairs added to each pentanomial count when estimating the distribution of scores
+const PSEUDO_COUNT: f64 = 0.5;
+
+/// The number of pairs of games (one with each colour) in which the first player scored 0, 0.5,
+/// 1, 1.5 and 2 points
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Pentanomial(pub [u32; 5]);
+
+impl Pentanomial {
+    pub fn get_pairs(&self) -> u32 {
+        self.0.iter().sum()
+    }
+
+    /// Record a pair of games in which the first player scored `points` (from 0 to 2)
+    pub fn add(&mut self, points: f64) {
+        self.0[(points * 2.0).round() as usize] += 1;
+    }
+
+    /// Return the log-likelihood ratio of `sprt`'s H1 to its H0, by the normal approximation of
+    /// the generalised SPRT to the scores of the pairs
+    ///
+    /// Each count is padded by `PSEUDO_COUNT` so that the variance is never 0, and a one-sided
+    /// run of results (e.g. every pair won) still reaches a decision, just not after a few pairs.
+    pub fn get_llr(&self, sprt: &Sprt) -> f64 {
+        let pairs = self.get_pairs() as f64;
+        if pairs == 0.0 {
+            return 0.0;
+        }
+        let scores = [0.0, 0.25, 0.5, 0.75, 1.0];
+        let padded_pairs = pairs + PSEUDO_COUNT * scores.len() as f64;
+        let frequencies = self
+            .0
+            .iter()
+            .map(|&count| (count as f64 + PSEUDO_COUNT) / padded_pairs);
+        let mean: f64 = frequencies
+            .clone()
+            .zip(scores.iter())
+            .map(|(frequency, score)| frequency * score)
+            .sum();
+        let variance: f64 = frequencies
+            .zip(scores.iter())
+            .map(|(frequency, score)| frequency * (score - mean).powi(2))
+            .sum();
+        let (score0, score1) = (expected_score(sprt.elo0), expected_score(sprt.elo1));
+        pairs * (score1 - score0) * (2.0 * mean - score0 - score1) / (2.0 * variance)
+    }
+}
+
+/// How to run a match
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchSettings {
+    pub sprt: Sprt,
+    /// the number of pairs of games to play at once
+    pub threads: usize,
+    /// the most pairs of games to play before giving up without a decision
+    pub max_pairs: usize,
+    /// the positions to start each pair of games from, in a shuffled order (repeating once
+    /// all have been played), or an empty list for random openings of `random_plies` moves
+    pub openings: Vec<BoardState>,
+    pub random_plies: u32,
+    /// the seed the order of the openings is shuffled by
+    pub seed: u64,
+    pub time_control: Option<TimeControl>,
+    pub adjudication: Option<Adjudication>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            sprt: Sprt::default(),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            max_pairs: 10_000,
+            openings: vec![],
+            random_plies: 8,
+            seed: 0,
+            time_control: None,
+            adjudication: Some(Adjudication::default()),
+        }
+    }
+}
+
+/// The state of a match so far, from the first player's point of view
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MatchStatus {
+    pub sprt: Sprt,
+    pub score: Score,
+    pub pentanomial: Pentanomial,
+    pub llr: f64,
+    /// the hypothesis accepted, once the match is over
+    pub decision: Option<Hypothesis>,
+}
+
+impl Display for MatchStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (lower, upper) = self.sprt.get_bounds();
+        write!(
+            f,
+            "Games {} | W/D/L {}/{}/{} | Penta {:?} | LLR {:.2} ({:.2}, {:.2}) [{}, {}]",
+            self.score.get_games(),
+            self.score.wins,
+            self.score.draws,
+            self.score.losses,
+            self.pentanomial.0,
+            self.llr,
+            lower,
+            upper,
+            self.sprt.elo0,
+            self.sprt.elo1
+        )?;
+        if self.score.get_games() > 0 {
+            match self.score.get_elo() {
+                (elo, _) if elo.is_infinite() => write!(f, " | Elo {:+}", elo)?,
+                (elo, margin) => write!(f, " | Elo {:+.1} ± {:.1}", elo, margin)?,
+            }
+        }
+        match self.decision {
+            Some(Hypothesis::H0) => write!(f, " | H0 accepted"),
+            Some(Hypothesis::H1) => write!(f, " | H1 accepted"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Return the indices of `length` items in a random order, shuffled by `seed`
+fn shuffled_indices(length: usize, seed: u64) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..length).collect();
+    let mut seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    for i in (1..length).rev() {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        indices.swap(i, (seed >> 33) as usize % (i + 1));
+    }
+    indices
+}
+
+/// Play pairs of games between the players made from `specs` by `make_player`, each pair from
+/// the same opening with the colours swapped, until `settings.sprt` accepts a hypothesis about
+/// how much stronger the first player is (or `settings.max_pairs` have been played)
+///
+/// `report` is called on this thread with the status and games of each pair as it ends, and the
+/// final status is returned.
+pub fn run_match(
+    specs: [&str; 2],
+    make_player: &(dyn Fn(&str) -> Box<dyn Player> + Sync),
+    settings: &MatchSettings,
+    report: &mut dyn FnMut(&MatchStatus, &[Game; 2]),
+) -> io::Result<MatchStatus> {
+    let order = shuffled_indices(settings.openings.len(), settings.seed);
+    let play = |index: usize| -> io::Result<[Game; 2]> {
+        let opening = match order.len() {
+            0 => random_opening(settings.seed ^ index as u64, settings.random_plies),
+            length => settings.openings[order[index % length]].clone(),
+        };
+        let play_game = |white, black| {
+            let mut game = play_tournament_game(
+                make_player(white),
+                make_player(black),
+                opening.clone(),
+                settings.time_control.as_ref(),
+                settings.adjudication.as_ref(),
+            )?;
+            game.set_tag("Event", "Match");
+            game.set_tag("Round", &(index + 1).to_string());
+            Ok::<_, io::Error>(game)
+        };
+        Ok([
+            play_game(specs[0], specs[1])?,
+            play_game(specs[1], specs[0])?,
+        ])
+    };
+
+    let mut status = MatchStatus {
+        sprt: settings.sprt,
+        score: Score::default(),
+        pentanomial: Pentanomial::default(),
+        llr: 0.0,
+        decision: None,
+    };
+    run_in_parallel(
+        settings.threads,
+        settings.max_pairs,
+        &play,
+        &mut |_, games| {
+            let mut points = 0.0;
+            for (game, first_is_white) in games.iter().zip([true, false].iter()) {
+                let first_wins = match game.result {
+                    GameResult::WhiteWins => Some(*first_is_white),
+                    GameResult::BlackWins => Some(!first_is_white),
+                    GameResult::Draw | GameResult::Unfinished => None,
+                };
+                match first_wins {
+                    Some(true) => {
+                        status.score.wins += 1;
+                        points += 1.0;
+                    }
+                    Some(false) => status.score.losses += 1,
+                    None => {
+                        status.score.draws += 1;
+                        points += 0.5;
+                    }
+                }
+            }
+            status.pentanomial.add(points);
+            status.llr = status.pentanomial.get_llr(&settings.sprt);
+            status.decision = settings.sprt.get_decision(status.llr);
+            report(&status, &games);
+            status.decision.is_none()
+        },
+    )?;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        expected_score, run_match, shuffled_indices, Hypothesis, MatchSettings, Pentanomial, Sprt,
+    };
+    use crate::{ai::AiPlayer, Player};
+
+    #[test]
+    fn bounds_and_scores() {
+        let (lower, upper) = Sprt::default().get_bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+        assert_eq!(expected_score(0.0), 0.5);
+        assert!((expected_score(400.0) - 10.0 / 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn llr() {
+        let sprt = Sprt::default();
+        assert_eq!(Pentanomial::default().get_llr(&sprt), 0.0);
+        // an even score favours H0, and a strong one H1
+        let even = Pentanomial([0, 0, 7, 0, 0]).get_llr(&sprt);
+        assert!(even < 0.0);
+        assert_eq!(sprt.get_decision(even), None);
+        let even = Pentanomial([10, 20, 40, 20, 10]).get_llr(&sprt);
+        assert!((even + 0.136).abs() < 0.001);
+        assert_eq!(sprt.get_decision(even), None);
+        let even = Pentanomial([300, 600, 1200, 600, 300]).get_llr(&sprt);
+        assert_eq!(sprt.get_decision(even), Some(Hypothesis::H0));
+        let strong = Pentanomial([10, 20, 80, 60, 30]).get_llr(&sprt);
+        assert_eq!(sprt.get_decision(strong), Some(Hypothesis::H1));
+    }
+
+    #[test]
+    fn one_sided_results_are_decided() {
+        let sprt = Sprt::default();
+        assert_eq!(
+            sprt.get_decision(Pentanomial([0, 0, 0, 0, 3]).get_llr(&sprt)),
+            None
+        );
+        assert_eq!(
+            sprt.get_decision(Pentanomial([3, 0, 0, 0, 0]).get_llr(&sprt)),
+            None
+        );
+        for pairs in [20, 100, 1000].iter() {
+            let wins = Pentanomial([0, 0, 0, 0, *pairs]).get_llr(&sprt);
+            assert_eq!(sprt.get_decision(wins), Some(Hypothesis::H1));
+            let losses = Pentanomial([*pairs, 0, 0, 0, 0]).get_llr(&sprt);
+            assert_eq!(sprt.get_decision(losses), Some(Hypothesis::H0));
+        }
+    }
+
+    #[test]
+    fn shuffles_are_permutations() {
+        let mut indices = shuffled_indices(10, 7);
+        assert_ne!(indices, (0..10).collect::<Vec<_>>());
+        assert_eq!(indices, shuffled_indices(10, 7));
+        indices.sort_unstable();
+        assert_eq!(indices, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stronger_player_is_accepted() {
+        let make_player =
+            |spec: &str| -> Box<dyn Player> { Box::new(AiPlayer::new(spec.parse().unwrap())) };
+        let settings = MatchSettings {
+            sprt: Sprt {
+                elo0: 0.0,
+                elo1: 200.0,
+                alpha: 0.1,
+                beta: 0.1,
+            },
+            threads: 2,
+            max_pairs: 50,
+            random_plies: 4,
+            ..MatchSettings::default()
+        };
+        let mut pairs = 0;
+        let status = run_match(["2", "1"], &make_player, &settings, &mut |status, games| {
+            pairs += 1;
+            assert_eq!(status.pentanomial.get_pairs(), pairs);
+            assert_eq!(games[0].get_tag("White"), Some("AI (depth 2)"));
+            assert_eq!(games[1].get_tag("White"), Some("AI (depth 1)"));
+        })
+        .unwrap();
+        assert_eq!(status.decision, Some(Hypothesis::H1));
+        assert_eq!(status.score.get_games(), 2 * status.pentanomial.get_pairs());
+    }
+}
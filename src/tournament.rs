@@ -0,0 +1,566 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use crate::{
+    board::{
+        grid::board_iterator,
+        outcome::{DrawReason, GameOutcome, WinReason},
+        piece::Colour::*,
+        BoardState,
+    },
+    clock::TimeControl,
+    game::{Game, GameResult},
+    new_game, play_adjudicated_game, Player,
+};
+
+/// When to end a game early, judging by the material each player has (in Pawns)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Adjudication {
+    /// a player resigns once they have been this far behind for `resign_plies` plies in a row
+    pub resign_margin: f64,
+    pub resign_plies: usize,
+    /// the game is drawn from this ply on, once material has been within `draw_margin` for
+    /// `draw_plies` plies in a row
+    pub draw_after_ply: usize,
+    pub draw_margin: f64,
+    pub draw_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            resign_margin: 5.0,
+            resign_plies: 6,
+            draw_after_ply: 120,
+            draw_margin: 1.0,
+            draw_plies: 20,
+        }
+    }
+}
+
+impl Adjudication {
+    /// Return the outcome the game should be ended with early, if any
+    fn get_outcome(&self, board_state: &BoardState) -> Option<GameOutcome> {
+        if self.should_resign(board_state) {
+            Some(GameOutcome::Win {
+                winner: !board_state.get_next_player(),
+                reason: WinReason::Adjudication,
+            })
+        } else if self.is_drawn(board_state) {
+            Some(GameOutcome::Draw(DrawReason::Adjudication))
+        } else {
+            None
+        }
+    }
+
+    /// Return true iff the player to move has lost
+    fn should_resign(&self, board_state: &BoardState) -> bool {
+        let sign = match board_state.get_next_player() {
+            White => 1.0,
+            Black => -1.0,
+        };
+        last_material_balances(board_state, self.resign_plies)
+            .is_some_and(|balances| balances.iter().all(|b| sign * b <= -self.resign_margin))
+    }
+
+    /// Return true iff the game should be drawn
+    fn is_drawn(&self, board_state: &BoardState) -> bool {
+        board_state.get_move_records().len() >= self.draw_after_ply
+            && last_material_balances(board_state, self.draw_plies)
+                .is_some_and(|balances| balances.iter().all(|b| b.abs() <= self.draw_margin))
+    }
+}
+
+/// Return White's material advantage in Pawns
+fn material_balance(board_state: &BoardState) -> f64 {
+    board_iterator()
+        .filter_map(|(&row, &column)| board_state.get_board()[row][column])
+        .map(|piece| piece.get_value() * 100.0)
+        .sum()
+}
+
+/// Return White's material advantage in each of the last `plies` positions, or None if fewer
+/// moves have been made
+fn last_material_balances(board_state: &BoardState, plies: usize) -> Option<Vec<f64>> {
+    if board_state.get_move_records().len() < plies {
+        return None;
+    }
+    let mut board_state = board_state.clone();
+    let mut balances = vec![material_balance(&board_state)];
+    for _ in 1..plies {
+        board_state.undo_move();
+        balances.push(material_balance(&board_state));
+    }
+    Some(balances)
+}
+
+/// How to run a tournament
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentSettings {
+    /// the number of games each pair of players plays, alternating colours
+    pub games_per_pairing: u32,
+    /// the number of games to play at once
+    pub threads: usize,
+    /// the number of random moves to start each pair of games with, so that deterministic
+    /// players do not repeat the same game
+    pub random_plies: u32,
+    pub time_control: Option<TimeControl>,
+    pub adjudication: Option<Adjudication>,
+}
+
+impl Default for TournamentSettings {
+    fn default() -> Self {
+        TournamentSettings {
+            games_per_pairing: 2,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            random_plies: 4,
+            time_control: None,
+            adjudication: Some(Adjudication::default()),
+        }
+    }
+}
+
+/// A game of a tournament, between the players with the given indices
+#[derive(Debug, Clone, PartialEq)]
+pub struct TournamentGame {
+    pub white: usize,
+    pub black: usize,
+    pub game: Game,
+}
+
+/// Return the position after `plies` random legal moves, chosen by `seed`
+pub(crate) fn random_opening(seed: u64, plies: u32) -> BoardState {
+    let mut board_state = BoardState::default();
+    let mut seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..plies {
+        let player = board_state.get_next_player();
+        let legal_moves = board_state.get_legal_moves(player);
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        let m = legal_moves[(seed >> 33) as usize % legal_moves.len()];
+        board_state
+            .try_move(m)
+            .expect("A legal move should be legal");
+        if board_state.outcome().is_some() {
+            break;
+        }
+    }
+    board_state
+}
+
+/// Play a round robin between the players made from `specs` by `make_player` (each game with
+/// new players, on one of `settings.threads` threads), calling `report` on this thread as each
+/// game ends, and returning the games in the order they were scheduled
+///
+/// Note: each pair of games between two players starts from the same random opening, with the
+/// colours swapped.
+pub fn run_tournament(
+    specs: &[String],
+    make_player: &(dyn Fn(&str) -> Box<dyn Player> + Sync),
+    settings: &TournamentSettings,
+    report: &mut dyn FnMut(&TournamentGame),
+) -> io::Result<Vec<TournamentGame>> {
+    let mut schedule = vec![];
+    for (pairing, (i, j)) in (0..specs.len())
+        .flat_map(|i| (i + 1..specs.len()).map(move |j| (i, j)))
+        .enumerate()
+    {
+        for game in 0..settings.games_per_pairing {
+            let (white, black) = if game % 2 == 0 { (i, j) } else { (j, i) };
+            let seed = (pairing as u64) << 32 | (game / 2) as u64;
+            schedule.push((white, black, seed));
+        }
+    }
+
+    let mut games: Vec<Option<TournamentGame>> = vec![None; schedule.len()];
+    let play = |index: usize| {
+        let (white, black, seed) = schedule[index];
+        play_tournament_game(
+            make_player(&specs[white]),
+            make_player(&specs[black]),
+            random_opening(seed, settings.random_plies),
+            settings.time_control.as_ref(),
+            settings.adjudication.as_ref(),
+        )
+        .map(|game| TournamentGame { white, black, game })
+    };
+    run_in_parallel(
+        settings.threads,
+        schedule.len(),
+        &play,
+        &mut |index, game| {
+            report(&game);
+            games[index] = Some(game);
+            true
+        },
+    )?;
+    Ok(games.into_iter().flatten().collect())
+}
+
+/// Call `play` with each index from 0 up to `limit` on `threads` threads, passing each result to
+/// `report` on this thread as it arrives, until `report` returns false or `play` fails
+///
+/// Note: once stopped, the calls already running are waited for, but their results ignored.
+pub(crate) fn run_in_parallel<T: Send>(
+    threads: usize,
+    limit: usize,
+    play: &(dyn Fn(usize) -> io::Result<T> + Sync),
+    report: &mut dyn FnMut(usize, T) -> bool,
+) -> io::Result<()> {
+    let next_index = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut error = None;
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (next_index, stop) = (&next_index, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    if index >= limit || sender.send((index, play(index))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (index, result) in receiver {
+            if stop.load(Ordering::Relaxed) {
+                continue;
+            }
+            match result {
+                Ok(result) => {
+                    if !report(index, result) {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+                Err(play_error) => {
+                    stop.store(true, Ordering::Relaxed);
+                    error = Some(play_error);
+                }
+            }
+        }
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Play a game from `board_state` between the players, ending it early by `adjudication` if
+/// given
+pub(crate) fn play_tournament_game(
+    white_player: Box<dyn Player>,
+    black_player: Box<dyn Player>,
+    board_state: BoardState,
+    time_control: Option<&TimeControl>,
+    adjudication: Option<&Adjudication>,
+) -> io::Result<Game> {
+    let mut game = new_game(
+        board_state,
+        &(*white_player),
+        &(*black_player),
+        time_control.cloned(),
+    );
+    game.set_tag("Event", "Tournament");
+    play_adjudicated_game(game, &*white_player, &*black_player, None, &|board_state| {
+        adjudication.and_then(|adjudication| adjudication.get_outcome(board_state))
+    })
+}
+
+/// Return the Elo rating difference that gives an expected `score` (from 0 to 1)
+pub fn elo_difference(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// The wins, draws and losses of one player against another
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Score {
+    pub fn get_games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn get_points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    /// Return the Elo difference the score suggests, and the margin of its 95% confidence
+    /// interval
+    ///
+    /// Note: the difference is infinite for a score of 0% or 100%, as is the margin when the
+    /// interval reaches either.
+    pub fn get_elo(&self) -> (f64, f64) {
+        let games = self.get_games() as f64;
+        let score = self.get_points() / games;
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let margin = 1.96 * (variance / games).sqrt();
+        let low = elo_difference((score - margin).max(0.0));
+        let high = elo_difference((score + margin).min(1.0));
+        (elo_difference(score), (high - low) / 2.0)
+    }
+}
+
+impl std::ops::AddAssign for Score {
+    fn add_assign(&mut self, other: Self) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+/// The scores of each player of a tournament against each other, written as a table ranked by
+/// points, with each player's Elo difference against the rest of the field
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crosstable {
+    names: Vec<String>,
+    /// `scores[i][j]` is the score of player i against player j
+    scores: Vec<Vec<Score>>,
+}
+
+impl Crosstable {
+    pub fn new(names: Vec<String>, games: &[TournamentGame]) -> Self {
+        let mut scores = vec![vec![Score::default(); names.len()]; names.len()];
+        for game in games {
+            let (white, black) = (game.white, game.black);
+            match game.game.result {
+                GameResult::WhiteWins => {
+                    scores[white][black].wins += 1;
+                    scores[black][white].losses += 1;
+                }
+                GameResult::BlackWins => {
+                    scores[white][black].losses += 1;
+                    scores[black][white].wins += 1;
+                }
+                GameResult::Draw => {
+                    scores[white][black].draws += 1;
+                    scores[black][white].draws += 1;
+                }
+                GameResult::Unfinished => {}
+            }
+        }
+        Crosstable { names, scores }
+    }
+
+    /// Return the score of `player` against `opponent`
+    pub fn get_score(&self, player: usize, opponent: usize) -> Score {
+        self.scores[player][opponent]
+    }
+
+    /// Return the score of `player` against every other player
+    pub fn get_total(&self, player: usize) -> Score {
+        let mut total = Score::default();
+        for &score in self.scores[player].iter() {
+            total += score;
+        }
+        total
+    }
+}
+
+impl Display for Crosstable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut ranking: Vec<usize> = (0..self.names.len()).collect();
+        ranking.sort_by(|&a, &b| {
+            let points = |player| self.get_total(player).get_points();
+            points(b).total_cmp(&points(a))
+        });
+        let name_width = self.names.iter().map(String::len).max().unwrap_or(0).max(6);
+        write!(f, "{:>3} {:<width$}", "#", "Player", width = name_width)?;
+        for rank in 1..=ranking.len() {
+            write!(f, " {:>7}", rank)?;
+        }
+        writeln!(f, " {:>9} {:>16}", "Score", "Elo")?;
+        for (rank, &player) in ranking.iter().enumerate() {
+            write!(
+                f,
+                "{:>3} {:<width$}",
+                rank + 1,
+                self.names[player],
+                width = name_width
+            )?;
+            for &opponent in ranking.iter() {
+                let score = self.get_score(player, opponent);
+                let cell = match (opponent == player, score.get_games()) {
+                    (true, _) => String::from("-"),
+                    (false, 0) => String::new(),
+                    (false, games) => format!("{}/{}", score.get_points(), games),
+                };
+                write!(f, " {:>7}", cell)?;
+            }
+            let total = self.get_total(player);
+            let elo = match total.get_games() {
+                0 => String::new(),
+                _ => match total.get_elo() {
+                    // all wins or all losses
+                    (elo, _) if elo.is_infinite() => format!("{:+}", elo),
+                    (elo, margin) => format!("{:+.0} ± {:.0}", elo, margin),
+                },
+            };
+            writeln!(
+                f,
+                " {:>9} {:>16}",
+                format!("{}/{}", total.get_points(), total.get_games()),
+                elo
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        elo_difference, play_tournament_game, random_opening, run_tournament, Adjudication,
+        Crosstable, Score, TournamentSettings,
+    };
+    use crate::{
+        ai::AiPlayer,
+        board::{
+            outcome::{DrawReason, GameOutcome, WinReason},
+            piece::Colour::*,
+            BoardState,
+        },
+        game::GameResult,
+        Player,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn elo() {
+        assert_eq!(elo_difference(0.5), 0.0);
+        assert!((elo_difference(0.75) - 190.85).abs() < 0.01);
+        assert!((elo_difference(0.25) + 190.85).abs() < 0.01);
+        let score = Score {
+            wins: 6,
+            draws: 2,
+            losses: 2,
+        };
+        let (elo, margin) = score.get_elo();
+        assert!((elo - 147.19).abs() < 0.01);
+        assert!(margin > 100.0 && margin < 400.0);
+    }
+
+    #[test]
+    fn adjudication() {
+        let adjudication = Adjudication {
+            resign_plies: 2,
+            draw_after_ply: 2,
+            draw_plies: 2,
+            ..Adjudication::default()
+        };
+        // Black has been a Queen down for two plies
+        let mut board_state = BoardState::from_str("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1").unwrap();
+        assert!(!adjudication.should_resign(&board_state));
+        board_state.try_move("a1a2".parse().unwrap()).unwrap();
+        assert!(!adjudication.should_resign(&board_state));
+        board_state.try_move("e8d8".parse().unwrap()).unwrap();
+        assert!(!adjudication.should_resign(&board_state));
+        board_state.try_move("a2a3".parse().unwrap()).unwrap();
+        assert!(adjudication.should_resign(&board_state));
+        assert!(!adjudication.is_drawn(&board_state));
+
+        let mut board_state = BoardState::default();
+        for m in ["e2e4", "e7e5"].iter() {
+            board_state.try_move(m.parse().unwrap()).unwrap();
+        }
+        assert!(adjudication.is_drawn(&board_state));
+        assert!(!adjudication.should_resign(&board_state));
+    }
+
+    #[test]
+    fn adjudicated_games() {
+        let adjudication = Adjudication {
+            resign_plies: 2,
+            draw_after_ply: 2,
+            draw_plies: 2,
+            ..Adjudication::default()
+        };
+        let play = |fen: &str| {
+            play_tournament_game(
+                Box::new(AiPlayer::new(1)),
+                Box::new(AiPlayer::new(1)),
+                BoardState::from_str(fen).unwrap(),
+                None,
+                Some(&adjudication),
+            )
+            .unwrap()
+        };
+
+        let game = play("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1");
+        assert_eq!(
+            game.outcome,
+            Some(GameOutcome::Win {
+                winner: White,
+                reason: WinReason::Adjudication,
+            })
+        );
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.get_tag("Termination"), Some("adjudication"));
+
+        let game = play(&BoardState::default().to_fen());
+        assert_eq!(
+            game.outcome,
+            Some(GameOutcome::Draw(DrawReason::Adjudication))
+        );
+        assert_eq!(game.result, GameResult::Draw);
+        assert_eq!(game.get_move_records().len(), 2);
+        assert_eq!(game.get_tag("Termination"), Some("adjudication"));
+    }
+
+    #[test]
+    fn openings_are_random_but_repeatable() {
+        assert_eq!(random_opening(1, 4), random_opening(1, 4));
+        assert_ne!(random_opening(1, 4), random_opening(2, 4));
+        assert_eq!(random_opening(1, 4).get_move_records().len(), 4);
+    }
+
+    #[test]
+    fn round_robin() {
+        let specs: Vec<String> = ["1", "1", "2"].iter().map(|&s| String::from(s)).collect();
+        let make_player =
+            |spec: &str| -> Box<dyn Player> { Box::new(AiPlayer::new(spec.parse().unwrap())) };
+        let settings = TournamentSettings {
+            games_per_pairing: 2,
+            threads: 2,
+            adjudication: Some(Adjudication {
+                draw_after_ply: 20,
+                ..Adjudication::default()
+            }),
+            ..TournamentSettings::default()
+        };
+        let mut reported = 0;
+        let games =
+            run_tournament(&specs, &make_player, &settings, &mut |_| reported += 1).unwrap();
+        assert_eq!(reported, 6);
+        let pairings: Vec<(usize, usize)> = games.iter().map(|g| (g.white, g.black)).collect();
+        assert_eq!(pairings, [(0, 1), (1, 0), (0, 2), (2, 0), (1, 2), (2, 1)]);
+        assert!(games
+            .iter()
+            .all(|game| game.game.result != GameResult::Unfinished));
+        // each pair of games starts from the same opening
+        assert_eq!(games[0].game.start, games[1].game.start);
+        assert_ne!(games[0].game.start, games[2].game.start);
+
+        let crosstable = Crosstable::new(specs, &games);
+        let total_points: f64 = (0..3).map(|i| crosstable.get_total(i).get_points()).sum();
+        assert_eq!(total_points, 6.0);
+        assert_eq!(crosstable.to_string().lines().count(), 4);
+    }
+}